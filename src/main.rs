@@ -1,5 +1,11 @@
+use sma_update_parser::modules::config::ParserConfig;
+use sma_update_parser::modules::digest::Crc16;
+use sma_update_parser::modules::index::Up2Index;
 use sma_update_parser::modules::parse::{Up2Parser};
-use sma_update_parser::modules::types::ModuleContent;
+use sma_update_parser::modules::read::IoReader;
+use sma_update_parser::modules::susy::SusyCatalog;
+use sma_update_parser::modules::types::{ChecksumMode, ModuleContent, ParseOptions, Up2File};
+use sma_update_parser::modules::verify::{firmware_blocks, Check, BLOCK_SIZE};
 
 // A CLI util to parse a SMA update file
 use clap::{Parser, Subcommand};
@@ -11,6 +17,9 @@ use std::io::prelude::*;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// A file with extra SUSy-ID names, one `id, family, model` entry per line
+    #[arg(long, global = true)]
+    susy_file: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -21,22 +30,91 @@ enum Commands {
         path: String,
         /// The path to dump the raw firmware to (optional)
         dump: Option<String>,
+        /// Print login passwords instead of redacting them
+        #[arg(long)]
+        show_secrets: bool,
+        /// Skip over corrupted modules and continue with the next valid one
+        #[arg(long)]
+        recover: bool,
     },
+    /// Lists the modules in an update file without reading their contents
+    List {
+        /// The path to the update file
+        path: String,
+    },
+    /// Recomputes the Adler-32, CRC-16 and MD4 of every FwChk module over the firmware blocks it covers
+    Verify {
+        /// The path to the update file
+        path: String,
+        /// The CRC-16 variant to check against, e.g. "CRC-16/X-25", the CRC is not checked without it
+        #[arg(long)]
+        crc: Option<String>,
+    },
+    /// Finds the CRC-16 variants that give the checksum of each FwChk module
+    IdentifyCrc {
+        /// The path to the update file
+        path: String,
+    },
+}
+
+// Formats the result of a single FwChk digest
+fn check_result<T: PartialEq>(check: &Check<T>, format: impl Fn(&T) -> String) -> String {
+    if check.passed() {
+        "ok".to_string()
+    } else {
+        format!(
+            "FAILED (expected {}, got {})",
+            format(&check.expected),
+            format(&check.actual)
+        )
+    }
+}
+
+// Formats a SUSy-ID with the device name if it is known
+fn susy_name(catalog: &SusyCatalog, id: u32) -> String {
+    match catalog.describe(id) {
+        Some(entry) => format!("{} (0x{:04x})", entry, id),
+        None => format!("0x{:04x}", id),
+    }
 }
 
 fn main() {
     let args = Cli::parse();
 
+    let mut catalog = SusyCatalog::new();
+    if let Some(path) = &args.susy_file {
+        catalog
+            .load_file(path)
+            .expect("Unable to load SUSy-ID file");
+    }
+
     match args.command {
-        Commands::Parse { path, dump } => {
+        Commands::Parse {
+            path,
+            dump,
+            show_secrets,
+            recover,
+        } => {
             // Open the file
             let file = File::open(path).expect("Unable to open file");
-            let reader = std::io::BufReader::new(file);
+            let reader = IoReader::new(std::io::BufReader::new(file));
 
             // Parse the header
-            let parser = Up2Parser::new(Box::new(reader)).unwrap();
+            let options = ParseOptions {
+                checksum_mode: ChecksumMode::Lenient,
+                recover,
+                ..Default::default()
+            };
+            let mut parser = Up2Parser::new(reader)
+                .unwrap()
+                .with_config(ParserConfig::from(options));
 
             let header = parser.header;
+            // The blocks of all firmware modules are dumped into the same file
+            let mut dump_file =
+                dump.map(|path| File::create(path).expect("Unable to create file"));
+            // Block numbers continue across firmware modules
+            let mut next_block = None;
 
             // Print the header
             println!("Header ID: 0x{:x}", header.header_id);
@@ -45,12 +123,24 @@ fn main() {
             println!("Build Number: {}", header.build_number);
             println!("Revision: {}", header.rev);
 
-            for module in parser {
+            for module in &mut parser {
                 if let Err(e) = module {
                     eprintln!("Error parsing module: {}", e);
                     continue;
                 }
                 let module = module.unwrap();
+                for warning in &module.warnings {
+                    eprintln!("Warning: {}", warning);
+                }
+                if let Some(data_header) = module.content.data_header() {
+                    println!("SMA Data header: {}", data_header);
+                    println!(
+                        "Target: {}, device type: {}, source: {}",
+                        data_header.target(),
+                        susy_name(&catalog, data_header.dst_susy.into()),
+                        susy_name(&catalog, data_header.src_susy.into())
+                    );
+                }
                 match module.content {
                     ModuleContent::Firmwarever(firmwarever) => {
                         println!("Firmware Version: {:#?}", firmwarever);
@@ -74,7 +164,15 @@ fn main() {
                         println!("Text: {:#?}", text.data);
                     }
                     ModuleContent::Login(login) => {
+                        // The password is redacted in the debug output
                         println!("Login: {:#?}", login);
+                        println!("Login parameters: {}", login.params());
+                        if show_secrets {
+                            match login.decoded_password() {
+                                Some(password) => println!("Password: {:?}", password),
+                                None => println!("Password (raw): {:02x?}", login.password),
+                            }
+                        }
                     }
                     ModuleContent::FwChk(fw_chk) => {
                         println!("FwChk: {:#?}", fw_chk);
@@ -84,24 +182,27 @@ fn main() {
                     }
                     ModuleContent::Firmware(firmware) => {
                         // the firmware for some devices consists of blocks that contain a 4 bytes uint that seems to be the block count, then 128 bytes of data
-                        let mut blocks = Vec::new();
-                        for (i, byte) in firmware.data.chunks(132).enumerate() {
-                            let p0 = u32::from_le_bytes([byte[0], byte[1], byte[2], byte[3]]);
-                            assert_eq!(
-                                i as u32, p0,
-                                "Block count does not match p0 present in block"
-                            );
-                            // Remove the p0 bytes
-                            let block = byte[4..].to_vec();
-                            blocks.push(block);
+                        let mut blocks = 0;
+                        for (number, block) in firmware_blocks(&firmware) {
+                            if let Some(expected) = next_block.filter(|&n| n != number) {
+                                eprintln!(
+                                    "Warning: block {} found where block {} was expected",
+                                    number, expected
+                                );
+                            }
+                            next_block = number.checked_add(1);
+                            blocks += 1;
+                            if let Some(file) = &mut dump_file {
+                                file.write_all(block).expect("Unable to write file");
+                            }
                         }
-                        println!("Firmware: {} blocks", blocks.len());
-                        // If dump_blocks is set, join the blocks and dump them to the specified path
-                        if let Some(ref dump_path) = dump {
-                            let mut file = File::create(dump_path).expect("Unable to create file");
-                            file.write_all(&blocks.concat())
-                                .expect("Unable to write file");
+                        if (1..4).contains(&(firmware.data.len() % BLOCK_SIZE)) {
+                            eprintln!(
+                                "Warning: ignoring {} bytes after the last block",
+                                firmware.data.len() % BLOCK_SIZE
+                            );
                         }
+                        println!("Firmware: {} blocks", blocks);
                     }
                     ModuleContent::Logout(logout) => {
                         println!("Logout: {:#?}", logout);
@@ -112,6 +213,85 @@ fn main() {
                     ModuleContent::Unknown(unknown) => {
                         eprintln!("Unknown module: {:#?}", unknown);
                     }
+                    ModuleContent::Custom(custom) => {
+                        println!("Custom: {:#?}", custom);
+                    }
+                }
+            }
+            for range in parser.skipped() {
+                eprintln!("Skipped bytes 0x{:x}..0x{:x}", range.start, range.end);
+            }
+        }
+        Commands::List { path } => {
+            let file = File::open(path).expect("Unable to open file");
+            let index = Up2Index::new(std::io::BufReader::new(file)).unwrap();
+            for (i, entry) in index.entries().iter().enumerate() {
+                println!(
+                    "{:4} 0x{:08x} {:?} susyid={} len={}",
+                    i,
+                    entry.span.header_offset,
+                    entry.module_type(),
+                    susy_name(&catalog, entry.header.susyid),
+                    entry.span.len
+                );
+            }
+        }
+        Commands::Verify { path, crc } => {
+            let crc = crc.map(|name| Crc16::by_name(&name).expect("Unknown CRC-16 variant"));
+            let file = File::open(path).expect("Unable to open file");
+            let file = Up2File::parse(IoReader::new(std::io::BufReader::new(file))).expect("Unable to parse file");
+            let reports = match &crc {
+                Some(crc) => file.verify_fw_chks_with(crc),
+                None => file.verify_fw_chks(),
+            };
+            if reports.is_empty() {
+                println!("No FwChk modules found");
+            }
+            for report in &reports {
+                println!(
+                    "FwChk module {}: blocks {}..={}, {} of {} found",
+                    report.module_index,
+                    report.blk_first,
+                    report.blk_last,
+                    report.blocks_found,
+                    report.blocks_expected()
+                );
+                println!(
+                    "  Adler-32: {}",
+                    check_result(&report.adler32, |v| format!("0x{:08x}", v))
+                );
+                match (&crc, &report.crc) {
+                    (Some(crc), Some(check)) => println!(
+                        "  {}: {}",
+                        crc.name,
+                        check_result(check, |v| format!("0x{:04x}", v))
+                    ),
+                    _ => println!("  CRC-16: unchecked, pass --crc to check it"),
+                }
+                println!(
+                    "  MD4: {}",
+                    check_result(&report.md4, |v| v.iter().map(|b| format!("{:02x}", b)).collect())
+                );
+            }
+            if !reports.iter().all(|r| r.passed()) {
+                std::process::exit(1);
+            }
+        }
+        Commands::IdentifyCrc { path } => {
+            let file = File::open(path).expect("Unable to open file");
+            let file = Up2File::parse(IoReader::new(std::io::BufReader::new(file))).expect("Unable to parse file");
+            let results = file.identify_crc16();
+            if results.is_empty() {
+                println!("No FwChk modules found");
+            }
+            for (module_index, matches) in results {
+                println!("FwChk module {}:", module_index);
+                if matches.is_empty() {
+                    println!("  No matching CRC-16 variant");
+                }
+                for m in matches {
+                    let swapped = if m.byte_swapped { " (byte swapped)" } else { "" };
+                    println!("  {}{}", m.variant.name, swapped);
                 }
             }
         }
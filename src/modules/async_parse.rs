@@ -0,0 +1,142 @@
+use futures_util::stream::{self, Stream};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::config::ParserConfig;
+use super::error::{Result, Up2Error};
+use super::parse::{check_header, check_limits, parse_module, parse_module_header};
+use super::types::{Module, ModuleRef, ModuleType, Up2Header};
+
+/// Parses an up2 file from a tokio `AsyncRead`
+///
+/// Module bodies are decoded with the same code as [`Up2Parser`](super::parse::Up2Parser).
+pub struct AsyncUp2Parser<R> {
+    reader: R,
+    offset: usize,
+    module_index: usize,
+    config: ParserConfig,
+    /// Set after an error that leaves the reader in the middle of a module
+    finished: bool,
+    pub header: Up2Header,
+}
+
+impl<R: AsyncRead + Unpin> AsyncUp2Parser<R> {
+    pub async fn new(mut reader: R) -> Result<Self> {
+        let buf = read_up_to(&mut reader, 8).await?;
+        let Some(buf) = buf.first_chunk() else {
+            return Err(Up2Error::TruncatedHeader {
+                module_index: None,
+                offset: 0,
+            });
+        };
+        let header = check_header(buf)?;
+        Ok(AsyncUp2Parser {
+            reader,
+            offset: buf.len(),
+            module_index: 0,
+            config: ParserConfig::default(),
+            finished: false,
+            header,
+        })
+    }
+
+    /// Returns the absolute byte offset of the next module in the file
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Sets the options and decoders, see [`ParserConfig`]
+    ///
+    /// Fails with [`Up2Error::Unsupported`] if
+    /// [`ParseOptions::recover`](super::types::ParseOptions::recover) is set, this parser can
+    /// not search for the next module after a broken one.
+    pub fn with_config(mut self, config: ParserConfig) -> Result<Self> {
+        if config.options.recover {
+            return Err(Up2Error::Unsupported("Recovery"));
+        }
+        self.config = config;
+        Ok(self)
+    }
+
+    /// Reads the next module, returns `None` at the end of the file
+    pub async fn next_module(&mut self) -> Option<Result<Module>> {
+        if self.finished {
+            return None;
+        }
+        let module_index = self.module_index;
+        let offset = self.offset;
+        let buf = match read_up_to(&mut self.reader, 16).await {
+            // End of file
+            Ok(buf) if buf.is_empty() => return None,
+            Ok(buf) => buf,
+            Err(e) => {
+                self.finished = true;
+                return Some(Err(Up2Error::from(e).in_module(module_index, None, offset)));
+            }
+        };
+        let Some(buf) = buf.first_chunk() else {
+            self.offset += buf.len();
+            return Some(Err(Up2Error::TruncatedHeader {
+                module_index: Some(module_index),
+                offset,
+            }));
+        };
+        self.module_index += 1;
+
+        let header = parse_module_header(buf);
+        if let Err(e) = check_limits(&self.config.options, &header, module_index, offset) {
+            self.finished = true;
+            return Some(Err(e));
+        }
+        let module_type = ModuleType::from_id(header.module_type);
+        let buf = match read_up_to(&mut self.reader, header.len as usize).await {
+            Ok(buf) => buf,
+            Err(e) => {
+                // The reader is somewhere in the middle of the module body
+                self.finished = true;
+                let e = Up2Error::from(e).in_module(module_index, Some(module_type), offset);
+                return Some(Err(e));
+            }
+        };
+        let read = buf.len();
+        self.offset += 16 + read;
+        if read < header.len as usize {
+            return Some(Err(Up2Error::TruncatedBody {
+                module_index,
+                module_type,
+                offset,
+                expected: header.len,
+                actual: read,
+            }));
+        }
+        Some(
+            parse_module(
+                header,
+                &buf,
+                module_index,
+                offset,
+                self.config.options.checksum_mode,
+                &self.config.decoders,
+            )
+            .map(ModuleRef::into_owned),
+        )
+    }
+
+    /// Turns the parser into a stream of modules
+    pub fn into_stream(self) -> impl Stream<Item = Result<Module>> {
+        stream::unfold(self, |mut parser| async move {
+            let module = parser.next_module().await?;
+            Some((module, parser))
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Reads up to `len` bytes, the buffer only grows as data actually arrives
+async fn read_up_to<R: AsyncRead + Unpin>(reader: &mut R, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.take(len as u64).read_to_end(&mut buf).await?;
+    Ok(buf)
+}
@@ -0,0 +1,218 @@
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::decoder::{CustomModule, CustomModuleContent};
+use super::span::ModuleSpan;
+use super::types::{
+    CondChkModule, FirmwareModule, FirmwareverModule, FwChkModule, LevelEndModule,
+    LevelStartModule, LoginModule, LogoutModule, LoopEndModule, LoopStartModule, Module,
+    ModuleContent, ModuleHeader, PauseModule, SmaDataHeader, TextModule, UnknownModule, Up2File,
+    Up2Header, UpFmt10Module,
+};
+use super::verify::BLOCK_SIZE;
+use super::write::encode_module_body;
+
+/// The most firmware data [`Up2Builder::firmware`] puts into one module, a whole number of blocks
+pub const MAX_FIRMWARE_DATA: usize = (u16::MAX as usize - 4) / BLOCK_SIZE * BLOCK_SIZE;
+
+/// Authors new up2 files module by module
+///
+/// The module header length and Adler-32 checksum as well as the `dat_len` field of
+/// SMA Data modules are filled in automatically, so they can be left at 0.
+#[derive(Debug, Clone)]
+pub struct Up2Builder {
+    header: Up2Header,
+    susyid: u32,
+    modules: Vec<(u32, u32, ModuleContent)>,
+}
+
+impl Default for Up2Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Up2Builder {
+    pub fn new() -> Self {
+        Up2Builder {
+            header: Up2Header {
+                // "SMA:"
+                header_id: 977358163,
+                major_version: 0,
+                minor_version: 0,
+                build_number: 0,
+                rev: 0,
+            },
+            susyid: 0,
+            modules: vec![],
+        }
+    }
+
+    /// Sets the version in the file header
+    pub fn version(
+        mut self,
+        major_version: u8,
+        minor_version: u8,
+        build_number: u8,
+        rev: u8,
+    ) -> Self {
+        self.header.major_version = major_version;
+        self.header.minor_version = minor_version;
+        self.header.build_number = build_number;
+        self.header.rev = rev;
+        self
+    }
+
+    /// Sets the SUSy-ID that is written to the header of all following modules
+    pub fn susyid(mut self, susyid: u32) -> Self {
+        self.susyid = susyid;
+        self
+    }
+
+    /// Appends a module with the type that matches its content
+    pub fn module(self, content: ModuleContent) -> Self {
+        let module_type = content.module_type().id();
+        self.raw_module(module_type, content)
+    }
+
+    /// Appends a module with an explicit type id, e.g. for unknown modules
+    pub fn raw_module(mut self, module_type: u32, content: ModuleContent) -> Self {
+        self.modules.push((module_type, self.susyid, content));
+        self
+    }
+
+    pub fn level_start(self, label: u32) -> Self {
+        self.module(ModuleContent::LevelStart(LevelStartModule { label }))
+    }
+
+    pub fn level_end(self, label: u32) -> Self {
+        self.module(ModuleContent::LevelEnd(LevelEndModule { label }))
+    }
+
+    pub fn pause(self, delay: u32) -> Self {
+        self.module(ModuleContent::Pause(PauseModule { delay }))
+    }
+
+    pub fn loop_start(self, label: u32) -> Self {
+        self.module(ModuleContent::LoopStart(LoopStartModule { label }))
+    }
+
+    pub fn loop_end(self, label: u32, loops: u32) -> Self {
+        self.module(ModuleContent::LoopEnd(LoopEndModule { label, loops }))
+    }
+
+    pub fn firmware_version(
+        self,
+        major_version: u8,
+        minor_version: u8,
+        build_number: u8,
+        rev: u8,
+    ) -> Self {
+        self.module(ModuleContent::Firmwarever(FirmwareverModule {
+            major_version,
+            minor_version,
+            build_number,
+            rev,
+        }))
+    }
+
+    pub fn text(self, data: &str) -> Self {
+        self.module(ModuleContent::Text(TextModule {
+            data: data.to_string(),
+        }))
+    }
+
+    pub fn login(self, mut login: LoginModule) -> Self {
+        // p1, p2, p3, password and mode
+        login.data_header.dat_len = 28;
+        self.module(ModuleContent::Login(login))
+    }
+
+    pub fn fw_chk(self, mut fw_chk: FwChkModule) -> Self {
+        // blk_first, blk_last, cond_cnt, crc, adler32 and md4
+        fw_chk.data_header.dat_len = 32;
+        self.module(ModuleContent::FwChk(fw_chk))
+    }
+
+    pub fn cond_chk(self, mut cond_chk: CondChkModule) -> Self {
+        // obj_nr until res_2
+        cond_chk.data_header.dat_len = 24;
+        self.module(ModuleContent::CondChk(cond_chk))
+    }
+
+    /// Appends the firmware data, split into several modules if it does not fit into one
+    ///
+    /// `dat_len` covers `delay` and the data and is only 16 bits wide, so every module holds at most
+    /// [`MAX_FIRMWARE_DATA`] bytes. The data is split at firmware block boundaries and every module
+    /// gets the same SMA Data header and delay.
+    pub fn firmware(mut self, firmware: FirmwareModule) -> Self {
+        if firmware.data.is_empty() {
+            return self.firmware_module(firmware.data_header, firmware.delay, vec![]);
+        }
+        for data in firmware.data.chunks(MAX_FIRMWARE_DATA) {
+            self = self.firmware_module(firmware.data_header, firmware.delay, data.to_vec());
+        }
+        self
+    }
+
+    fn firmware_module(self, mut data_header: SmaDataHeader, delay: u32, data: Vec<u8>) -> Self {
+        // delay and the firmware data
+        data_header.dat_len = (4 + data.len()) as u16;
+        self.module(ModuleContent::Firmware(FirmwareModule {
+            data_header,
+            delay,
+            data,
+        }))
+    }
+
+    pub fn logout(self, mut logout: LogoutModule) -> Self {
+        logout.data_header.dat_len = 0;
+        self.module(ModuleContent::Logout(logout))
+    }
+
+    pub fn up_fmt10(self, data: Vec<u8>) -> Self {
+        self.module(ModuleContent::UpFmt10(UpFmt10Module { data }))
+    }
+
+    pub fn unknown(self, module_type: u32, data: Vec<u8>) -> Self {
+        self.module(ModuleContent::Unknown(UnknownModule { module_type, data }))
+    }
+
+    /// Appends a module that is encoded by `value`, see [`CustomModule`]
+    pub fn custom(self, module_type: u32, value: impl CustomModule) -> Self {
+        self.module(ModuleContent::Custom(CustomModuleContent::new(
+            module_type,
+            value,
+        )))
+    }
+
+    /// Creates the file, with module headers and spans matching what `Up2File::to_bytes` will write
+    pub fn build(self) -> Up2File {
+        let mut offset = 8;
+        let mut modules = vec![];
+        for (module_type, susyid, content) in self.modules {
+            let body = encode_module_body(&content);
+            modules.push(Module {
+                header: ModuleHeader {
+                    adler: adler32::RollingAdler32::from_buffer(&body).hash(),
+                    module_type,
+                    susyid,
+                    len: body.len() as u32,
+                },
+                content,
+                span: ModuleSpan {
+                    header_offset: offset,
+                    body_offset: offset + 16,
+                    len: body.len(),
+                },
+                warnings: vec![],
+            });
+            offset += 16 + body.len();
+        }
+        Up2File {
+            header: self.header,
+            modules,
+        }
+    }
+}
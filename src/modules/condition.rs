@@ -0,0 +1,138 @@
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+use super::types::CondChkModule;
+
+// How the fields of a CondChk module are used comes from reverse engineering, the handling of
+// `no_obj` and `dat_valid` in particular is an interpretation that may have to be refined.
+
+/// Identifies the object value a CondChk module looks at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ObjectKey {
+    pub obj_nr: u16,
+    /// Index of the dword in a record, from `rec_dw_first`
+    pub rec_dw: u16,
+    /// Index of the value, from `idx_first`
+    pub index: u32,
+}
+
+/// Current value of an object on a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectValue {
+    Value(u32),
+    /// The device has the object, but no valid value for it
+    Invalid,
+}
+
+/// Supplies the current object values of a device, e.g. from a snapshot
+pub trait ObjectSource {
+    /// Returns the value of an object, `None` if the device does not have the object
+    fn value(&self, key: ObjectKey) -> Option<ObjectValue>;
+}
+
+impl<F> ObjectSource for F
+where
+    F: Fn(ObjectKey) -> Option<ObjectValue>,
+{
+    fn value(&self, key: ObjectKey) -> Option<ObjectValue> {
+        self(key)
+    }
+}
+
+impl ObjectSource for BTreeMap<ObjectKey, ObjectValue> {
+    fn value(&self, key: ObjectKey) -> Option<ObjectValue> {
+        self.get(&key).copied()
+    }
+}
+
+/// Why a condition passed or failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CondReason {
+    /// The value after applying `bitmask` is within `lo_bound..=hi_bound`
+    InRange { value: u32, masked: u32 },
+    /// The value after applying `bitmask` is outside of `lo_bound..=hi_bound`
+    OutOfRange { value: u32, masked: u32 },
+    /// The device does not have the object, the result is taken from `no_obj`
+    MissingObject,
+    /// The device has no valid value for the object, the result depends on `dat_valid`
+    InvalidData,
+}
+
+/// Result of evaluating a CondChk module
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CondResult {
+    /// The update continues
+    Proceed(CondReason),
+    /// The update is skipped for this device
+    Skip(CondReason),
+}
+
+impl CondResult {
+    pub fn proceeds(&self) -> bool {
+        matches!(self, CondResult::Proceed(_))
+    }
+
+    pub fn reason(&self) -> CondReason {
+        match self {
+            CondResult::Proceed(reason) | CondResult::Skip(reason) => *reason,
+        }
+    }
+}
+
+impl fmt::Display for CondResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CondResult::Proceed(_) => write!(f, "proceed: ")?,
+            CondResult::Skip(_) => write!(f, "skip: ")?,
+        }
+        match self.reason() {
+            CondReason::InRange { value, masked } => write!(
+                f,
+                "value 0x{:08x} (masked 0x{:08x}) is within the bounds",
+                value, masked
+            ),
+            CondReason::OutOfRange { value, masked } => write!(
+                f,
+                "value 0x{:08x} (masked 0x{:08x}) is outside of the bounds",
+                value, masked
+            ),
+            CondReason::MissingObject => write!(f, "the device does not have the object"),
+            CondReason::InvalidData => write!(f, "the device has no valid value for the object"),
+        }
+    }
+}
+
+impl CondChkModule {
+    /// Returns the object value the condition looks at
+    pub fn object_key(&self) -> ObjectKey {
+        ObjectKey {
+            obj_nr: self.obj_nr,
+            rec_dw: self.rec_dw_first,
+            index: self.idx_first,
+        }
+    }
+
+    /// Checks the condition against the current object values of a device
+    ///
+    /// The value is masked with `bitmask` and compared to `lo_bound..=hi_bound`. A missing
+    /// object passes if `no_obj` is set, an invalid value fails if `dat_valid` is set, i.e.
+    /// if the condition requires valid data.
+    pub fn evaluate(&self, objects: &impl ObjectSource) -> CondResult {
+        match objects.value(self.object_key()) {
+            None if self.no_obj != 0 => CondResult::Proceed(CondReason::MissingObject),
+            None => CondResult::Skip(CondReason::MissingObject),
+            Some(ObjectValue::Invalid) if self.dat_valid != 0 => {
+                CondResult::Skip(CondReason::InvalidData)
+            }
+            Some(ObjectValue::Invalid) => CondResult::Proceed(CondReason::InvalidData),
+            Some(ObjectValue::Value(value)) => {
+                let masked = value & self.bitmask;
+                if (self.lo_bound..=self.hi_bound).contains(&masked) {
+                    CondResult::Proceed(CondReason::InRange { value, masked })
+                } else {
+                    CondResult::Skip(CondReason::OutOfRange { value, masked })
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use super::decoder::{DecoderRegistry, ModuleDecoder};
+use super::types::{ChecksumMode, ParseOptions};
+
+/// Settings shared by all parsers, the [`ParseOptions`] and the decoders for module types
+///
+/// Passed to [`Up2Parser::with_config`](super::parse::Up2Parser::with_config),
+/// [`Up2Slice::with_config`](super::slice::Up2Slice::with_config), `Up2Index::new_with_config`
+/// and `AsyncUp2Parser::with_config`.
+#[derive(Debug, Clone, Default)]
+pub struct ParserConfig {
+    pub options: ParseOptions,
+    pub decoders: DecoderRegistry,
+}
+
+impl ParserConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how the Adler-32 checksum of each module is verified
+    pub fn with_checksum_mode(mut self, mode: ChecksumMode) -> Self {
+        self.options.checksum_mode = mode;
+        self
+    }
+
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Decodes modules with the given type id with `decoder` instead of the built-in decoding
+    pub fn with_decoder(mut self, module_type: u32, decoder: impl ModuleDecoder + 'static) -> Self {
+        self.decoders.register(module_type, decoder);
+        self
+    }
+
+    pub fn with_decoders(mut self, decoders: DecoderRegistry) -> Self {
+        self.decoders = decoders;
+        self
+    }
+}
+
+impl From<ParseOptions> for ParserConfig {
+    fn from(options: ParseOptions) -> Self {
+        ParserConfig {
+            options,
+            decoders: DecoderRegistry::default(),
+        }
+    }
+}
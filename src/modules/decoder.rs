@@ -0,0 +1,140 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+
+/// Decodes the body of a module type that is not built into this crate
+///
+/// Decoders are registered for a type id with
+/// [`ParserConfig::with_decoder`](super::config::ParserConfig::with_decoder). Closures with the
+/// same signature as [`ModuleDecoder::decode`] implement the trait as well. Decoders have to be
+/// `Clone` so a [`DecoderRegistry`] can be cloned.
+pub trait ModuleDecoder: Send + Sync + CloneModuleDecoder {
+    /// Decodes a module body, the error message ends up in [`Up2Error::Decode`](super::error::Up2Error)
+    fn decode(&self, body: &[u8]) -> Result<Box<dyn CustomModule>, String>;
+}
+
+impl<F> ModuleDecoder for F
+where
+    F: Fn(&[u8]) -> Result<Box<dyn CustomModule>, String> + Send + Sync + Clone + 'static,
+{
+    fn decode(&self, body: &[u8]) -> Result<Box<dyn CustomModule>, String> {
+        self(body)
+    }
+}
+
+/// Clones a boxed decoder, implemented for every [`ModuleDecoder`] that is `Clone`
+pub trait CloneModuleDecoder {
+    fn clone_box(&self) -> Box<dyn ModuleDecoder>;
+}
+
+impl<T: ModuleDecoder + Clone + 'static> CloneModuleDecoder for T {
+    fn clone_box(&self) -> Box<dyn ModuleDecoder> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn ModuleDecoder> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+/// A value returned by a [`ModuleDecoder`], it has to be `Clone` so modules can be cloned
+pub trait CustomModule: Any + fmt::Debug + Send + Sync + CloneCustomModule {
+    /// Encodes the value back into a module body, the inverse of the decoder
+    fn encode(&self) -> Vec<u8>;
+}
+
+/// Clones a boxed value, implemented for every [`CustomModule`] that is `Clone`
+pub trait CloneCustomModule {
+    fn clone_box(&self) -> Box<dyn CustomModule>;
+}
+
+impl<T: CustomModule + Clone> CloneCustomModule for T {
+    fn clone_box(&self) -> Box<dyn CustomModule> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn CustomModule> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+/// Content of a module that was decoded by a registered [`ModuleDecoder`]
+///
+/// Two custom modules are equal if they have the same type id, the same Rust type and encode to the same bytes.
+#[derive(Debug, Clone)]
+pub struct CustomModuleContent {
+    /// Raw type id from the module header
+    pub module_type: u32,
+    pub value: Box<dyn CustomModule>,
+}
+
+impl CustomModuleContent {
+    pub fn new(module_type: u32, value: impl CustomModule) -> Self {
+        CustomModuleContent {
+            module_type,
+            value: Box::new(value),
+        }
+    }
+
+    /// Returns the decoded value if it is a `T`
+    pub fn downcast_ref<T: CustomModule>(&self) -> Option<&T> {
+        let value: &dyn Any = &*self.value;
+        value.downcast_ref()
+    }
+}
+
+impl PartialEq for CustomModuleContent {
+    fn eq(&self, other: &Self) -> bool {
+        let a: &dyn Any = &*self.value;
+        let b: &dyn Any = &*other.value;
+        self.module_type == other.module_type
+            && a.type_id() == b.type_id()
+            && self.value.encode() == other.value.encode()
+    }
+}
+
+impl Eq for CustomModuleContent {}
+
+/// Decoders by module type id
+///
+/// Registered decoders take precedence over the built-in decoding of a type id.
+#[derive(Clone, Default)]
+pub struct DecoderRegistry {
+    decoders: BTreeMap<u32, Box<dyn ModuleDecoder>>,
+}
+
+impl DecoderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a decoder for a type id, replacing any decoder that was registered for it before
+    pub fn register(&mut self, module_type: u32, decoder: impl ModuleDecoder + 'static) {
+        self.decoders.insert(module_type, Box::new(decoder));
+    }
+
+    pub fn get(&self, module_type: u32) -> Option<&dyn ModuleDecoder> {
+        self.decoders.get(&module_type).map(|d| &**d)
+    }
+
+    pub fn contains(&self, module_type: u32) -> bool {
+        self.decoders.contains_key(&module_type)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decoders.is_empty()
+    }
+}
+
+impl fmt::Debug for DecoderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.decoders.keys()).finish()
+    }
+}
@@ -0,0 +1,317 @@
+/// Computes the MD4 digest (RFC 1320) of `data`
+pub fn md4(data: &[u8]) -> [u8; 16] {
+    let mut state = [0x67452301u32, 0xefcdab89, 0x98badcfe, 0x10325476];
+    let mut chunks = data.chunks_exact(64);
+    for chunk in &mut chunks {
+        md4_compress(&mut state, chunk.try_into().unwrap());
+    }
+
+    // Padding: a 1 bit, zeros and the length in bits, up to two blocks
+    let rest = chunks.remainder();
+    let mut tail = [0; 128];
+    tail[..rest.len()].copy_from_slice(rest);
+    tail[rest.len()] = 0x80;
+    let tail_len = if rest.len() < 56 { 64 } else { 128 };
+    let bit_len = (data.len() as u64).wrapping_mul(8);
+    tail[tail_len - 8..tail_len].copy_from_slice(&bit_len.to_le_bytes());
+    for chunk in tail[..tail_len].chunks_exact(64) {
+        md4_compress(&mut state, chunk.try_into().unwrap());
+    }
+
+    let mut digest = [0; 16];
+    for (out, word) in digest.chunks_exact_mut(4).zip(state) {
+        out.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+fn md4_compress(state: &mut [u32; 4], block: &[u8; 64]) {
+    let mut x = [0u32; 16];
+    for (word, bytes) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+    let mut v = *state;
+
+    // Every step updates the first variable and rotates them, so the next step updates the old last one
+    let mut step = |f: fn(u32, u32, u32) -> u32, k: usize, constant: u32, shift: u32| {
+        let t = v[0]
+            .wrapping_add(f(v[1], v[2], v[3]))
+            .wrapping_add(x[k])
+            .wrapping_add(constant)
+            .rotate_left(shift);
+        v = [v[3], t, v[1], v[2]];
+    };
+    for i in 0..16 {
+        step(|b, c, d| (b & c) | (!b & d), i, 0, [3, 7, 11, 19][i % 4]);
+    }
+    for i in 0..16 {
+        let k = (i % 4) * 4 + i / 4;
+        step(
+            |b, c, d| (b & c) | (b & d) | (c & d),
+            k,
+            0x5a827999,
+            [3, 5, 9, 13][i % 4],
+        );
+    }
+    for i in 0..16 {
+        let k = [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15][i];
+        step(|b, c, d| b ^ c ^ d, k, 0x6ed9eba1, [3, 9, 11, 15][i % 4]);
+    }
+
+    for (word, value) in state.iter_mut().zip(v) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+/// Parameters of a CRC-16 variant, in the usual catalogue notation
+///
+/// Which variant the checksum of FwChk modules uses is not known yet, [`identify_crc16`](super::verify::identify_crc16)
+/// finds the ones that match a real update file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc16 {
+    pub name: &'static str,
+    pub poly: u16,
+    pub init: u16,
+    /// Input bytes are processed least significant bit first
+    pub refin: bool,
+    /// The result is reflected before `xorout` is applied
+    pub refout: bool,
+    pub xorout: u16,
+}
+
+impl Crc16 {
+    pub const CCITT_FALSE: Crc16 = Crc16 {
+        name: "CRC-16/CCITT-FALSE",
+        poly: 0x1021,
+        init: 0xffff,
+        refin: false,
+        refout: false,
+        xorout: 0x0000,
+    };
+    pub const ARC: Crc16 = Crc16 {
+        name: "CRC-16/ARC",
+        poly: 0x8005,
+        init: 0x0000,
+        refin: true,
+        refout: true,
+        xorout: 0x0000,
+    };
+    pub const XMODEM: Crc16 = Crc16 {
+        name: "CRC-16/XMODEM",
+        poly: 0x1021,
+        init: 0x0000,
+        refin: false,
+        refout: false,
+        xorout: 0x0000,
+    };
+    pub const KERMIT: Crc16 = Crc16 {
+        name: "CRC-16/KERMIT",
+        poly: 0x1021,
+        init: 0x0000,
+        refin: true,
+        refout: true,
+        xorout: 0x0000,
+    };
+    pub const X_25: Crc16 = Crc16 {
+        name: "CRC-16/X-25",
+        poly: 0x1021,
+        init: 0xffff,
+        refin: true,
+        refout: true,
+        xorout: 0xffff,
+    };
+    pub const MODBUS: Crc16 = Crc16 {
+        name: "CRC-16/MODBUS",
+        poly: 0x8005,
+        init: 0xffff,
+        refin: true,
+        refout: true,
+        xorout: 0x0000,
+    };
+    pub const AUG_CCITT: Crc16 = Crc16 {
+        name: "CRC-16/AUG-CCITT",
+        poly: 0x1021,
+        init: 0x1d0f,
+        refin: false,
+        refout: false,
+        xorout: 0x0000,
+    };
+    pub const BUYPASS: Crc16 = Crc16 {
+        name: "CRC-16/BUYPASS",
+        poly: 0x8005,
+        init: 0x0000,
+        refin: false,
+        refout: false,
+        xorout: 0x0000,
+    };
+    pub const CDMA2000: Crc16 = Crc16 {
+        name: "CRC-16/CDMA2000",
+        poly: 0xc867,
+        init: 0xffff,
+        refin: false,
+        refout: false,
+        xorout: 0x0000,
+    };
+    pub const DDS_110: Crc16 = Crc16 {
+        name: "CRC-16/DDS-110",
+        poly: 0x8005,
+        init: 0x800d,
+        refin: false,
+        refout: false,
+        xorout: 0x0000,
+    };
+    pub const DECT_X: Crc16 = Crc16 {
+        name: "CRC-16/DECT-X",
+        poly: 0x0589,
+        init: 0x0000,
+        refin: false,
+        refout: false,
+        xorout: 0x0000,
+    };
+    pub const DNP: Crc16 = Crc16 {
+        name: "CRC-16/DNP",
+        poly: 0x3d65,
+        init: 0x0000,
+        refin: true,
+        refout: true,
+        xorout: 0xffff,
+    };
+    pub const EN_13757: Crc16 = Crc16 {
+        name: "CRC-16/EN-13757",
+        poly: 0x3d65,
+        init: 0x0000,
+        refin: false,
+        refout: false,
+        xorout: 0xffff,
+    };
+    pub const GENIBUS: Crc16 = Crc16 {
+        name: "CRC-16/GENIBUS",
+        poly: 0x1021,
+        init: 0xffff,
+        refin: false,
+        refout: false,
+        xorout: 0xffff,
+    };
+    pub const MAXIM: Crc16 = Crc16 {
+        name: "CRC-16/MAXIM",
+        poly: 0x8005,
+        init: 0x0000,
+        refin: true,
+        refout: true,
+        xorout: 0xffff,
+    };
+    pub const MCRF4XX: Crc16 = Crc16 {
+        name: "CRC-16/MCRF4XX",
+        poly: 0x1021,
+        init: 0xffff,
+        refin: true,
+        refout: true,
+        xorout: 0x0000,
+    };
+    pub const RIELLO: Crc16 = Crc16 {
+        name: "CRC-16/RIELLO",
+        poly: 0x1021,
+        init: 0xb2aa,
+        refin: true,
+        refout: true,
+        xorout: 0x0000,
+    };
+    pub const T10_DIF: Crc16 = Crc16 {
+        name: "CRC-16/T10-DIF",
+        poly: 0x8bb7,
+        init: 0x0000,
+        refin: false,
+        refout: false,
+        xorout: 0x0000,
+    };
+    pub const TELEDISK: Crc16 = Crc16 {
+        name: "CRC-16/TELEDISK",
+        poly: 0xa097,
+        init: 0x0000,
+        refin: false,
+        refout: false,
+        xorout: 0x0000,
+    };
+    pub const TMS37157: Crc16 = Crc16 {
+        name: "CRC-16/TMS37157",
+        poly: 0x1021,
+        init: 0x89ec,
+        refin: true,
+        refout: true,
+        xorout: 0x0000,
+    };
+    pub const USB: Crc16 = Crc16 {
+        name: "CRC-16/USB",
+        poly: 0x8005,
+        init: 0xffff,
+        refin: true,
+        refout: true,
+        xorout: 0xffff,
+    };
+    pub const A: Crc16 = Crc16 {
+        name: "CRC-16/A",
+        poly: 0x1021,
+        init: 0xc6c6,
+        refin: true,
+        refout: true,
+        xorout: 0x0000,
+    };
+
+    /// The common CRC-16 variants, used to find the variant of an unknown checksum
+    pub const CATALOGUE: &'static [Crc16] = &[
+        Crc16::ARC,
+        Crc16::CCITT_FALSE,
+        Crc16::XMODEM,
+        Crc16::KERMIT,
+        Crc16::X_25,
+        Crc16::MODBUS,
+        Crc16::AUG_CCITT,
+        Crc16::BUYPASS,
+        Crc16::CDMA2000,
+        Crc16::DDS_110,
+        Crc16::DECT_X,
+        Crc16::DNP,
+        Crc16::EN_13757,
+        Crc16::GENIBUS,
+        Crc16::MAXIM,
+        Crc16::MCRF4XX,
+        Crc16::RIELLO,
+        Crc16::T10_DIF,
+        Crc16::TELEDISK,
+        Crc16::TMS37157,
+        Crc16::USB,
+        Crc16::A,
+    ];
+
+    /// Looks up a variant of the catalogue by name, with or without the "CRC-16/" prefix
+    pub fn by_name(name: &str) -> Option<Crc16> {
+        let name = name.strip_prefix("CRC-16/").unwrap_or(name);
+        Crc16::CATALOGUE
+            .iter()
+            .find(|c| c.name["CRC-16/".len()..].eq_ignore_ascii_case(name))
+            .copied()
+    }
+
+    pub fn checksum(&self, data: &[u8]) -> u16 {
+        let mut crc = self.init;
+        for &byte in data {
+            let byte = if self.refin {
+                byte.reverse_bits()
+            } else {
+                byte
+            };
+            crc ^= u16::from(byte) << 8;
+            for _ in 0..8 {
+                crc = if crc & 0x8000 != 0 {
+                    (crc << 1) ^ self.poly
+                } else {
+                    crc << 1
+                };
+            }
+        }
+        if self.refout {
+            crc = crc.reverse_bits();
+        }
+        crc ^ self.xorout
+    }
+}
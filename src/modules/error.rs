@@ -0,0 +1,258 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::fmt;
+
+use super::types::ModuleType;
+
+pub type Result<T> = core::result::Result<T, Up2Error>;
+
+/// Errors that can occur while parsing an up2 file
+///
+/// Offsets are absolute byte offsets into the file and point at the start of the module header,
+/// module indices count from 0 for the first module after the file header.
+#[derive(Debug)]
+pub enum Up2Error {
+    /// Reading from the underlying reader failed
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// A custom [`Up2Read`](super::read::Up2Read) implementation failed
+    Read(&'static str),
+    /// Reading a module failed, `source` is an [`Up2Error::Io`] or [`Up2Error::Read`]
+    ModuleRead {
+        module_index: usize,
+        /// `None` if the module header could not be read
+        module_type: Option<ModuleType>,
+        offset: usize,
+        source: Box<Up2Error>,
+    },
+    /// An option was set that the parser does not support
+    Unsupported(&'static str),
+    /// The file does not start with "SMA:"
+    BadMagic { header_id: u32 },
+    /// The file header (`module_index` is `None`) or a module header was cut off
+    TruncatedHeader {
+        module_index: Option<usize>,
+        offset: usize,
+    },
+    /// The file ended before the module body was complete
+    TruncatedBody {
+        module_index: usize,
+        module_type: ModuleType,
+        offset: usize,
+        expected: u32,
+        actual: usize,
+    },
+    /// The module body does not have a valid length for its type
+    BadModuleLength {
+        module_index: usize,
+        module_type: ModuleType,
+        offset: usize,
+        len: usize,
+    },
+    /// The module is longer than [`ParseOptions::max_module_size`](super::types::ParseOptions)
+    ModuleTooLarge {
+        module_index: usize,
+        module_type: ModuleType,
+        offset: usize,
+        len: u32,
+        max: usize,
+    },
+    /// The module would end after [`ParseOptions::max_file_size`](super::types::ParseOptions)
+    FileTooLarge {
+        module_index: usize,
+        module_type: ModuleType,
+        offset: usize,
+        max: usize,
+    },
+    /// A text field in the module is not valid UTF-8
+    InvalidUtf8 {
+        module_index: usize,
+        module_type: ModuleType,
+        offset: usize,
+    },
+    /// The Adler-32 checksum in the module header does not match the module body
+    ChecksumMismatch {
+        module_index: usize,
+        module_type: ModuleType,
+        offset: usize,
+        expected: u32,
+        actual: u32,
+    },
+    /// A registered [`ModuleDecoder`](super::decoder::ModuleDecoder) rejected the module body
+    Decode {
+        module_index: usize,
+        module_type: ModuleType,
+        offset: usize,
+        message: String,
+    },
+}
+
+impl Up2Error {
+    /// Checks if reading from the underlying reader failed, the parsers stop after such an error
+    pub fn is_read_error(&self) -> bool {
+        match self {
+            #[cfg(feature = "std")]
+            Up2Error::Io(_) => true,
+            Up2Error::Read(_) => true,
+            Up2Error::ModuleRead { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Attaches the position of the module that was being read to a read error
+    pub(crate) fn in_module(
+        self,
+        module_index: usize,
+        module_type: Option<ModuleType>,
+        offset: usize,
+    ) -> Self {
+        match self {
+            Up2Error::ModuleRead { .. } => self,
+            e if e.is_read_error() => Up2Error::ModuleRead {
+                module_index,
+                module_type,
+                offset,
+                source: Box::new(e),
+            },
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for Up2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Up2Error::Io(e) => write!(f, "I/O error: {}", e),
+            Up2Error::Read(e) => write!(f, "Read error: {}", e),
+            Up2Error::ModuleRead {
+                module_index,
+                module_type: Some(module_type),
+                offset,
+                source,
+            } => write!(
+                f,
+                "Reading module {} ({:?}) at offset 0x{:x} failed: {}",
+                module_index, module_type, offset, source
+            ),
+            Up2Error::ModuleRead {
+                module_index,
+                module_type: None,
+                offset,
+                source,
+            } => write!(
+                f,
+                "Reading the header of module {} at offset 0x{:x} failed: {}",
+                module_index, offset, source
+            ),
+            Up2Error::Unsupported(option) => write!(f, "{} is not supported by this parser", option),
+            Up2Error::BadMagic { header_id } => write!(
+                f,
+                "Invalid header id 0x{:08x}, file does not seem to be an up2 file",
+                header_id
+            ),
+            Up2Error::TruncatedHeader {
+                module_index: None,
+                offset,
+            } => write!(f, "File header truncated at offset 0x{:x}", offset),
+            Up2Error::TruncatedHeader {
+                module_index: Some(index),
+                offset,
+            } => write!(
+                f,
+                "Header of module {} truncated at offset 0x{:x}",
+                index, offset
+            ),
+            Up2Error::TruncatedBody {
+                module_index,
+                module_type,
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Body of module {} ({:?}) at offset 0x{:x} truncated: expected {} bytes, got {}",
+                module_index, module_type, offset, expected, actual
+            ),
+            Up2Error::BadModuleLength {
+                module_index,
+                module_type,
+                offset,
+                len,
+            } => write!(
+                f,
+                "Module {} ({:?}) at offset 0x{:x} has an invalid length of {} bytes",
+                module_index, module_type, offset, len
+            ),
+            Up2Error::ModuleTooLarge {
+                module_index,
+                module_type,
+                offset,
+                len,
+                max,
+            } => write!(
+                f,
+                "Module {} ({:?}) at offset 0x{:x} is {} bytes long, the maximum is {}",
+                module_index, module_type, offset, len, max
+            ),
+            Up2Error::FileTooLarge {
+                module_index,
+                module_type,
+                offset,
+                max,
+            } => write!(
+                f,
+                "Module {} ({:?}) at offset 0x{:x} ends after the maximum file size of {} bytes",
+                module_index, module_type, offset, max
+            ),
+            Up2Error::InvalidUtf8 {
+                module_index,
+                module_type,
+                offset,
+            } => write!(
+                f,
+                "Module {} ({:?}) at offset 0x{:x} contains invalid utf8",
+                module_index, module_type, offset
+            ),
+            Up2Error::ChecksumMismatch {
+                module_index,
+                module_type,
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Module {} ({:?}) at offset 0x{:x} has an Adler-32 checksum mismatch (expected 0x{:08x}, got 0x{:08x})",
+                module_index, module_type, offset, expected, actual
+            ),
+            Up2Error::Decode {
+                module_index,
+                module_type,
+                offset,
+                message,
+            } => write!(
+                f,
+                "Module {} ({:?}) at offset 0x{:x} could not be decoded: {}",
+                module_index, module_type, offset, message
+            ),
+        }
+    }
+}
+
+impl core::error::Error for Up2Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Up2Error::Io(e) => Some(e),
+            Up2Error::ModuleRead { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Up2Error {
+    fn from(e: std::io::Error) -> Self {
+        Up2Error::Io(e)
+    }
+}
@@ -0,0 +1,81 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::error::Result;
+use super::parse::Up2Parser;
+use super::read::Up2Read;
+use super::slice::Up2Slice;
+use super::smadata::Target;
+use super::types::{FirmwareverModule, Module, ModuleContent, ModuleRef, ModuleType, Up2File};
+
+impl Module {
+    pub fn module_type(&self) -> ModuleType {
+        ModuleType::from_id(self.header.module_type)
+    }
+}
+
+impl Up2File {
+    /// Reads a complete up2 file, failing on the first module that can not be parsed
+    pub fn parse<R: Up2Read>(reader: R) -> Result<Self> {
+        let parser = Up2Parser::new(reader)?;
+        let header = parser.header;
+        Ok(Up2File {
+            header,
+            modules: parser.collect::<Result<_>>()?,
+        })
+    }
+
+    /// Parses a complete up2 file that is already in memory
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let parser = Up2Slice::new(data)?;
+        let header = parser.header;
+        Ok(Up2File {
+            header,
+            modules: parser
+                .map(|module| module.map(ModuleRef::into_owned))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Returns all modules of the given type, in file order
+    pub fn modules_of_type(&self, module_type: ModuleType) -> impl Iterator<Item = &Module> {
+        self.modules
+            .iter()
+            .filter(move |module| module.module_type() == module_type)
+    }
+
+    /// Returns the firmware version the file contains, if it has a firmware version module
+    pub fn firmware_version(&self) -> Option<&FirmwareverModule> {
+        self.modules
+            .iter()
+            .find_map(|module| match &module.content {
+                ModuleContent::Firmwarever(version) => Some(version),
+                _ => None,
+            })
+    }
+
+    /// Returns the contents of all text modules
+    pub fn texts(&self) -> impl Iterator<Item = &str> {
+        self.modules
+            .iter()
+            .filter_map(|module| match &module.content {
+                ModuleContent::Text(text) => Some(text.data.as_str()),
+                _ => None,
+            })
+    }
+
+    /// Returns every distinct destination that a command module is sent to
+    pub fn targets(&self) -> Vec<Target> {
+        let mut targets = vec![];
+        for module in &self.modules {
+            let Some(data_header) = module.content.data_header() else {
+                continue;
+            };
+            let target = data_header.target();
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        targets
+    }
+}
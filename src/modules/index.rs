@@ -0,0 +1,133 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use super::config::ParserConfig;
+use super::error::{Result, Up2Error};
+use super::parse::{check_limits, parse_module, parse_module_header, read_full, read_header};
+use super::read::IoReader;
+use super::span::ModuleSpan;
+use super::types::{Module, ModuleHeader, ModuleRef, ModuleType, Up2Header};
+
+/// A module that was found while scanning the file, without its body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub header: ModuleHeader,
+    pub span: ModuleSpan,
+}
+
+impl IndexEntry {
+    pub fn module_type(&self) -> ModuleType {
+        ModuleType::from_id(self.header.module_type)
+    }
+}
+
+/// Index of all modules in a seekable up2 file
+///
+/// Creating the index only reads the module headers and seeks past the bodies,
+/// bodies are only read and decoded when a module is loaded.
+pub struct Up2Index<R: Read + Seek> {
+    reader: R,
+    /// Position of the up2 file in the reader
+    start: u64,
+    config: ParserConfig,
+    entries: Vec<IndexEntry>,
+    pub header: Up2Header,
+}
+
+impl<R: Read + Seek> Up2Index<R> {
+    /// Scans the file, the reader has to be positioned at the start of the up2 file
+    pub fn new(reader: R) -> Result<Self> {
+        Self::new_with_config(reader, ParserConfig::default())
+    }
+
+    /// Scans the file, rejecting modules that are over the limits in the options of `config`
+    ///
+    /// The checksum mode and the decoders of `config` are used when modules are loaded.
+    pub fn new_with_config(mut reader: R, config: ParserConfig) -> Result<Self> {
+        let start = reader.stream_position()?;
+        let end = (reader.seek(SeekFrom::End(0))? - start) as usize;
+        reader.seek(SeekFrom::Start(start))?;
+        let header = read_header(&mut IoReader::new(&mut reader))?;
+
+        let mut entries = vec![];
+        let mut offset = 8;
+        while offset < end {
+            let module_index = entries.len();
+            let mut buf = [0; 16];
+            if read_full(&mut IoReader::new(&mut reader), &mut buf)? != buf.len() {
+                return Err(Up2Error::TruncatedHeader {
+                    module_index: Some(module_index),
+                    offset,
+                });
+            }
+            let module_header = parse_module_header(&buf);
+            let module_len = check_limits(&config.options, &module_header, module_index, offset)?;
+            let len = module_header.len as usize;
+            let available = end - offset - 16;
+            if len > available {
+                return Err(Up2Error::TruncatedBody {
+                    module_index,
+                    module_type: ModuleType::from_id(module_header.module_type),
+                    offset,
+                    expected: module_header.len,
+                    actual: available,
+                });
+            }
+            entries.push(IndexEntry {
+                header: module_header,
+                span: ModuleSpan {
+                    header_offset: offset,
+                    body_offset: offset + 16,
+                    len,
+                },
+            });
+            reader.seek(SeekFrom::Current(len as i64))?;
+            offset += module_len;
+        }
+
+        Ok(Up2Index {
+            reader,
+            start,
+            config,
+            entries,
+            header,
+        })
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Reads and decodes the module with the given index, returns `None` if there is no such module
+    pub fn load(&mut self, module_index: usize) -> Option<Result<Module>> {
+        let entry = *self.entries.get(module_index)?;
+        Some(self.load_entry(module_index, &entry))
+    }
+
+    fn load_entry(&mut self, module_index: usize, entry: &IndexEntry) -> Result<Module> {
+        let mut buf = vec![0; entry.span.len];
+        let position = self.start + entry.span.body_offset as u64;
+        self.reader
+            .seek(SeekFrom::Start(position))
+            .and_then(|_| self.reader.read_exact(&mut buf))
+            .map_err(|e| {
+                Up2Error::from(e).in_module(
+                    module_index,
+                    Some(entry.module_type()),
+                    entry.span.header_offset,
+                )
+            })?;
+        parse_module(
+            entry.header,
+            &buf,
+            module_index,
+            entry.span.header_offset,
+            self.config.options.checksum_mode,
+            &self.config.decoders,
+        )
+        .map(ModuleRef::into_owned)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
@@ -0,0 +1,182 @@
+use alloc::string::String;
+use core::fmt;
+use core::time::Duration;
+
+use super::types::LoginModule;
+
+/// The user group a login is done with, it also selects how the password is encoded
+///
+/// Only the codes of the user and installer groups are known, other groups like the service
+/// group are kept as [`UserGroup::Other`] with their code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UserGroup {
+    User,
+    Installer,
+    /// A user group that is not known, with its raw value
+    Other(u32),
+}
+
+impl UserGroup {
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            0x07 => UserGroup::User,
+            0x0a => UserGroup::Installer,
+            _ => UserGroup::Other(code),
+        }
+    }
+
+    pub fn code(self) -> u32 {
+        match self {
+            UserGroup::User => 0x07,
+            UserGroup::Installer => 0x0a,
+            UserGroup::Other(code) => code,
+        }
+    }
+
+    /// The byte that is added to every password character, `None` for unknown user groups
+    ///
+    /// This is often described as XOR, which only gives the same result for characters where
+    /// bit 3 and bit 7 are not set.
+    pub fn password_key(self) -> Option<u8> {
+        match self {
+            UserGroup::User => Some(0x88),
+            UserGroup::Installer => Some(0xbb),
+            UserGroup::Other(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for UserGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UserGroup::User => write!(f, "user"),
+            UserGroup::Installer => write!(f, "installer"),
+            UserGroup::Other(code) => write!(f, "0x{:02x}", code),
+        }
+    }
+}
+
+impl fmt::Debug for LoginModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoginModule")
+            .field("data_header", &self.data_header)
+            .field("p1", &self.p1)
+            .field("p2", &self.p2)
+            .field("p3", &self.p3)
+            .field("password", &"<redacted>")
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+impl LoginModule {
+    /// Returns the user group from `p0` of the SMA Data header
+    ///
+    /// SMA Data logon packets carry the user group, timeout, timestamp and a zero in front of the
+    /// password, which are `p0` to `p3` here. `mode` after the password usually has the same
+    /// value as `p0`, but its meaning is not known.
+    pub fn user_group(&self) -> UserGroup {
+        UserGroup::from_code(self.data_header.p0)
+    }
+
+    /// Decodes the password with the encoding of the user group
+    ///
+    /// The encoding is selected by the user group in `p0`, not by `mode`. The logon packet only
+    /// carries the user group in `p0`, `mode` usually has the same value but is not used for this.
+    ///
+    /// Returns `None` if the user group is not known or the password is not valid UTF-8 after decoding.
+    pub fn decoded_password(&self) -> Option<String> {
+        let key = self.user_group().password_key()?;
+        let decoded = self.password.map(|b| b.wrapping_sub(key));
+        // Unused bytes are encoded zeros
+        let len = decoded
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(decoded.len());
+        core::str::from_utf8(&decoded[..len]).ok().map(String::from)
+    }
+
+    /// Encodes a password for the password field, passwords are cut off after 12 bytes
+    ///
+    /// Unknown user groups get the password without any encoding.
+    pub fn encode_password(user_group: UserGroup, password: &str) -> [u8; 12] {
+        let key = user_group.password_key().unwrap_or(0);
+        let mut buf = [key; 12];
+        for (b, c) in buf.iter_mut().zip(password.bytes()) {
+            *b = c.wrapping_add(key);
+        }
+        buf
+    }
+}
+
+/// A unix timestamp in seconds, shown as a UTC date and time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(pub u32);
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.0 / 86400;
+        let seconds = self.0 % 86400;
+        // Converts days since 1970-01-01 to a date, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            year,
+            month,
+            day,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+/// Typed view of the login parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoginParams {
+    /// From `p0`
+    pub user_group: UserGroup,
+    /// How long the session stays open without packets, from `p1`
+    pub timeout: Duration,
+    /// When the login was created, from `p2`
+    pub timestamp: Timestamp,
+    /// Meaning not known, usually 0
+    pub p3: u32,
+    /// Meaning not known, usually the same as the user group
+    pub mode: u32,
+}
+
+impl fmt::Display for LoginParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "user group: {}, timeout: {} s, time: {}, p3: 0x{:08x}, mode: 0x{:08x}",
+            self.user_group,
+            self.timeout.as_secs(),
+            self.timestamp,
+            self.p3,
+            self.mode
+        )
+    }
+}
+
+impl LoginModule {
+    pub fn params(&self) -> LoginParams {
+        LoginParams {
+            user_group: self.user_group(),
+            timeout: Duration::from_secs(self.p1.into()),
+            timestamp: Timestamp(self.p2),
+            p3: self.p3,
+            mode: self.mode,
+        }
+    }
+}
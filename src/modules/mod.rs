@@ -1,2 +1,21 @@
+#[cfg(feature = "async")]
+pub mod async_parse;
+pub mod builder;
+pub mod condition;
+pub mod config;
+pub mod decoder;
+pub mod digest;
+pub mod error;
+pub mod file;
+#[cfg(feature = "std")]
+pub mod index;
+pub mod login;
 pub mod parse;
+pub mod read;
+pub mod slice;
+pub mod smadata;
+pub mod span;
+pub mod susy;
 pub mod types;
+pub mod verify;
+pub mod write;
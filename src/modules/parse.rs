@@ -1,11 +1,20 @@
-use std::cell::RefCell;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
 
-use anyhow::{bail, Result, ensure};
+use super::config::ParserConfig;
+use super::decoder::{CustomModuleContent, DecoderRegistry};
+use super::error::{Result, Up2Error};
+use super::read::Up2Read;
+use super::span::ModuleSpan;
 use super::types;
-use super::types::{Module, ModuleContent, ModuleType, Up2Header};
+use super::types::{
+    ChecksumMode, Module, ModuleContentRef, ModuleHeader, ModuleRef, ModuleType, ModuleWarning,
+    ParseOptions, SmaDataHeader, Up2Header,
+};
 
 // Takes an up2 file (as a slice of bytes) and returns a header struct
-pub fn parse_header(buf: &[u8]) -> Up2Header {
+pub fn parse_header(buf: &[u8; 8]) -> Up2Header {
     // Create a new header struct
     let mut header = Up2Header {
         header_id: 0,
@@ -26,324 +35,663 @@ pub fn parse_header(buf: &[u8]) -> Up2Header {
     header
 }
 
-pub struct Up2Parser {
-    reader: RefCell<Box<dyn std::io::Read>>,
+// Takes the 16 bytes in front of every module and returns a module header struct
+pub fn parse_module_header(buf: &[u8; 16]) -> ModuleHeader {
+    ModuleHeader {
+        adler: u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+        module_type: u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
+        susyid: u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]),
+        len: u32::from_le_bytes([buf[12], buf[13], buf[14], buf[15]]),
+    }
+}
+
+impl SmaDataHeader {
+    /// Decodes the 28 byte header at the start of a command module body
+    pub fn decode(buf: &[u8; 28]) -> Self {
+        SmaDataHeader {
+            ctrl: u16::from_le_bytes([buf[0], buf[1]]),
+            dst_susy: u16::from_le_bytes([buf[2], buf[3]]),
+            dst_ser: u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
+            dst_dev: buf[8],
+            dst_fkt: buf[9],
+            src_susy: u16::from_le_bytes([buf[10], buf[11]]),
+            src_ser: u32::from_le_bytes([buf[12], buf[13], buf[14], buf[15]]),
+            src_dev: buf[16],
+            src_fkt: buf[17],
+            cmd: buf[18],
+            pcnt: buf[19],
+            obj_num: u16::from_le_bytes([buf[20], buf[21]]),
+            dat_len: u16::from_le_bytes([buf[22], buf[23]]),
+            p0: u32::from_le_bytes([buf[24], buf[25], buf[26], buf[27]]),
+        }
+    }
+}
+
+/// Verifies the checksum and decodes the body of a module whose header starts at `offset`
+pub(crate) fn parse_module<'a>(
+    header: ModuleHeader,
+    body: &'a [u8],
+    module_index: usize,
     offset: usize,
-    pub header: Up2Header,
+    checksum_mode: ChecksumMode,
+    decoders: &DecoderRegistry,
+) -> Result<ModuleRef<'a>> {
+    let module_type = ModuleType::from_id(header.module_type);
+    let mut warnings = vec![];
+    if checksum_mode != ChecksumMode::Off {
+        let actual = adler32::RollingAdler32::from_buffer(body).hash();
+        if actual != header.adler {
+            if checksum_mode == ChecksumMode::Strict {
+                return Err(Up2Error::ChecksumMismatch {
+                    module_index,
+                    module_type,
+                    offset,
+                    expected: header.adler,
+                    actual,
+                });
+            }
+            warnings.push(ModuleWarning::ChecksumMismatch {
+                expected: header.adler,
+                actual,
+            });
+        }
+    }
+    let content = match decoders.get(header.module_type) {
+        Some(decoder) => ModuleContentRef::Custom(CustomModuleContent {
+            module_type: header.module_type,
+            value: decoder.decode(body).map_err(|message| Up2Error::Decode {
+                module_index,
+                module_type,
+                offset,
+                message,
+            })?,
+        }),
+        None => parse_module_body(body, &module_type, module_index, offset)?,
+    };
+    Ok(ModuleRef {
+        header,
+        content,
+        span: ModuleSpan {
+            header_offset: offset,
+            body_offset: offset + 16,
+            len: body.len(),
+        },
+        warnings,
+    })
+}
+
+/// Checks a module header that starts at `offset` against the size limits
+///
+/// Returns the length of the module including its header, which is known to fit into a `usize`
+/// when this returns `Ok`.
+pub(crate) fn check_limits(
+    options: &ParseOptions,
+    header: &ModuleHeader,
+    module_index: usize,
+    offset: usize,
+) -> Result<usize> {
+    let len = header.len as usize;
+    let module_len = match len.checked_add(16) {
+        Some(module_len) if len <= options.max_module_size => module_len,
+        _ => {
+            return Err(Up2Error::ModuleTooLarge {
+                module_index,
+                module_type: ModuleType::from_id(header.module_type),
+                offset,
+                len: header.len,
+                max: options.max_module_size,
+            })
+        }
+    };
+    if offset.saturating_add(module_len) > options.max_file_size {
+        return Err(Up2Error::FileTooLarge {
+            module_index,
+            module_type: ModuleType::from_id(header.module_type),
+            offset,
+            max: options.max_file_size,
+        });
+    }
+    Ok(module_len)
+}
+
+/// Returns the body length of module types with a fixed layout
+fn fixed_body_len(module_type: ModuleType) -> Option<usize> {
+    match module_type {
+        ModuleType::LevelStartMt
+        | ModuleType::LevelEndMt
+        | ModuleType::PauseMt
+        | ModuleType::LoopStartMt
+        | ModuleType::FirmwareverMt => Some(4),
+        ModuleType::LoopEndMt => Some(8),
+        ModuleType::LoginMt => Some(56),
+        ModuleType::FwChkMt => Some(60),
+        ModuleType::CondChkMt => Some(52),
+        ModuleType::LogoutMt => Some(28),
+        _ => None,
+    }
 }
 
-impl Up2Parser {
-    pub fn new(reader: Box<dyn std::io::Read>) -> Result<Self> {
-        let mut parser = Up2Parser {
-            reader: RefCell::new(reader),
+/// Checks if a module header at `offset` could be the start of a valid module
+///
+/// Used to find the next module after corrupted data. Only cheap checks are done here,
+/// the checksum still has to be verified.
+pub(crate) fn plausible_header(
+    header: &ModuleHeader,
+    offset: usize,
+    options: &ParseOptions,
+    decoders: &DecoderRegistry,
+) -> bool {
+    if check_limits(options, header, 0, offset).is_err() {
+        return false;
+    }
+    if decoders.contains(header.module_type) {
+        return true;
+    }
+    let len = header.len as usize;
+    match ModuleType::from_id(header.module_type) {
+        ModuleType::Unknown(_) => false,
+        // The SMA Data header and the delay
+        ModuleType::FirmwareMt => len >= 32,
+        module_type => match fixed_body_len(module_type) {
+            Some(expected) => len == expected,
+            // Text and UpFmt10 modules, an empty one is more likely a run of zeros in other data
+            None => len > 0,
+        },
+    }
+}
+
+/// Returns how many body bytes are checksummed at most while searching for the next module
+///
+/// Without a limit a file with a plausible header at every offset would take quadratic time.
+pub(crate) fn resync_budget(options: &ParseOptions) -> usize {
+    options.max_module_size.saturating_mul(4)
+}
+
+pub(crate) fn checksum_matches(header: &ModuleHeader, body: &[u8]) -> bool {
+    adler32::RollingAdler32::from_buffer(body).hash() == header.adler
+}
+
+/// Reads the first 8 bytes of the file and checks that they are a valid up2 header
+pub(crate) fn read_header<R: Up2Read + ?Sized>(reader: &mut R) -> Result<Up2Header> {
+    let mut buf = [0; 8];
+    if read_full(reader, &mut buf)? != buf.len() {
+        return Err(Up2Error::TruncatedHeader {
+            module_index: None,
             offset: 0,
-            header: Up2Header { header_id: 0, major_version: 0, minor_version: 0, build_number: 0, rev: 0 }
-        };
-        parser.header = parser.load_header()?;
-        Ok(parser)
+        });
+    }
+    check_header(&buf)
+}
+
+/// Parses the file header and checks that it starts with "SMA:"
+pub(crate) fn check_header(buf: &[u8; 8]) -> Result<Up2Header> {
+    let header = parse_header(buf);
+    // 977358163 = "SMA:"
+    if header.header_id != 977358163 {
+        return Err(Up2Error::BadMagic {
+            header_id: header.header_id,
+        });
+    }
+    Ok(header)
+}
+
+/// Streaming parser over any [`Up2Read`] source
+///
+/// The parser is `Send` if the reader is, use [`Up2Parser::into_inner`] to get the reader back.
+pub struct Up2Parser<R> {
+    reader: R,
+    /// Bytes that were read from the reader, but not consumed yet
+    lookahead: Vec<u8>,
+    offset: usize,
+    module_index: usize,
+    config: ParserConfig,
+    /// Set after an error that leaves the reader in the middle of a module
+    finished: bool,
+    /// Set after an error in recovery mode, the next call searches for the next module
+    resync: bool,
+    skipped: Vec<Range<usize>>,
+    pub header: Up2Header,
+}
+
+impl<R: Up2Read> Up2Parser<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let header = read_header(&mut reader)?;
+        Ok(Up2Parser {
+            reader,
+            lookahead: vec![],
+            offset: 8,
+            module_index: 0,
+            config: ParserConfig::default(),
+            finished: false,
+            resync: false,
+            skipped: vec![],
+            header,
+        })
     }
 
-    fn load_header(&mut self) -> Result<Up2Header> {
-        // Read the first 8 bytes of the file
-        let bytes = &mut self.reader.borrow_mut();
-        let mut buf = [0; 8];
-        bytes.read_exact(&mut buf)?;
-        let header = parse_header(&buf);
-        // 977358163 = "SMA:"
-        if header.header_id != 977358163 {
-            bail!("Invalid header id, file does not seem to be an up2 file");
+    /// Returns the absolute byte offset of the next module in the file
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Sets the options and decoders, see [`ParserConfig`]
+    pub fn with_config(mut self, config: ParserConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the byte ranges that were skipped in recovery mode so far
+    pub fn skipped(&self) -> &[Range<usize>] {
+        &self.skipped
+    }
+
+    /// Returns the underlying reader
+    ///
+    /// The parser reads ahead, e.g. after an error in recovery mode, so the reader can be
+    /// positioned after the next module. Use [`Up2Parser::into_parts`] to get the bytes that
+    /// were read but not parsed yet as well.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Returns the underlying reader and the bytes that were read from it but not parsed yet
+    ///
+    /// The bytes start at [`Up2Parser::offset`] in the file.
+    pub fn into_parts(self) -> (R, Vec<u8>) {
+        (self.reader, self.lookahead)
+    }
+
+    /// Makes sure at least `len` bytes are in the lookahead buffer, returns how many there are
+    fn fill(&mut self, len: usize) -> Result<usize> {
+        if self.lookahead.len() < len {
+            let missing = len - self.lookahead.len();
+            read_append(&mut self.reader, &mut self.lookahead, missing)?;
         }
-        Ok(header)
+        Ok(self.lookahead.len())
     }
 
-    fn parse_module_body(
-        &self,
-        raw_data: &[u8],
-        module_type: &ModuleType,
-    ) -> Result<ModuleContent> {
-        let len = raw_data.len();
-        match module_type {
-            ModuleType::LevelStartMt => {
-                ensure!(len == 4, "Invalid level start module found!");
-                Ok(ModuleContent::LevelStart(types::LevelStartModule {
-                    label: u32::from_le_bytes([raw_data[0], raw_data[1], raw_data[2], raw_data[3]]),
-                }))
-            }
-            ModuleType::LevelEndMt => {
-                ensure!(len == 4, "Invalid level end module found!");
-                Ok(ModuleContent::LevelEnd(types::LevelEndModule {
-                    label: u32::from_le_bytes([raw_data[0], raw_data[1], raw_data[2], raw_data[3]]),
-                }))
-            }
-            ModuleType::PauseMt => {
-                ensure!(len == 4, "Invalid pause module found!");
-                Ok(ModuleContent::Pause(types::PauseModule {
-                    delay: u32::from_le_bytes([raw_data[0], raw_data[1], raw_data[2], raw_data[3]]),
-                }))
-            }
-            ModuleType::LoopStartMt => {
-                ensure!(len == 4, "Invalid loop start module found!");
-                Ok(ModuleContent::LoopStart(types::LoopStartModule {
-                    label: u32::from_le_bytes([raw_data[0], raw_data[1], raw_data[2], raw_data[3]]),
-                }))
-            }
-            ModuleType::LoopEndMt => {
-                ensure!(len == 8, "Invalid loop end module found!");
-                Ok(ModuleContent::LoopEnd(types::LoopEndModule {
-                    label: u32::from_le_bytes([raw_data[0], raw_data[1], raw_data[2], raw_data[3]]),
-                    loops: u32::from_le_bytes([raw_data[4], raw_data[5], raw_data[6], raw_data[7]]),
-                }))
-            }
-            ModuleType::FirmwareverMt => {
-                ensure!(len == 4, "Invalid firmware version module found!");
-                Ok(ModuleContent::Firmwarever(types::FirmwareverModule {
-                    major_version: raw_data[0],
-                    minor_version: raw_data[1],
-                    build_number: raw_data[2],
-                    rev: raw_data[3],
-                }))
+    /// Removes `len` bytes from the lookahead buffer
+    fn consume(&mut self, len: usize) {
+        self.lookahead.drain(..len);
+        self.offset += len;
+    }
+
+    fn read_module(&mut self, header: ModuleHeader, module_index: usize) -> Result<Module> {
+        let offset = self.offset;
+        let module_len = check_limits(&self.config.options, &header, module_index, offset)?;
+        let module_type = ModuleType::from_id(header.module_type);
+        let available = self
+            .fill(module_len)
+            .map_err(|e| e.in_module(module_index, Some(module_type), offset))?;
+        if available < module_len {
+            return Err(Up2Error::TruncatedBody {
+                module_index,
+                module_type,
+                offset,
+                expected: header.len,
+                actual: available - 16,
+            });
+        }
+        parse_module(
+            header,
+            &self.lookahead[16..module_len],
+            module_index,
+            offset,
+            self.config.options.checksum_mode,
+            &self.config.decoders,
+        )
+        .map(ModuleRef::into_owned)
+    }
+
+    /// Drops bytes until the next plausible module, returns `false` if the end of the file was reached
+    ///
+    /// Read errors are reported for the module that would have been found next.
+    fn resynchronize(&mut self) -> Result<bool> {
+        let start = self.offset;
+        let module_index = self.module_index;
+        let read_error = |e: Up2Error, offset| e.in_module(module_index, None, offset);
+        let mut budget = resync_budget(&self.config.options);
+        // The module at the current position is known to be broken
+        let mut pos = 1;
+        let found = loop {
+            // Don't keep the skipped bytes around while searching
+            if pos >= 64 * 1024 {
+                self.consume(pos);
+                pos = 0;
             }
-            ModuleType::TextMt => Ok(ModuleContent::Text(types::TextModule {
-                data: String::from_utf8(raw_data.to_vec())
-                    .expect("Found text module, but failed to convert to utf8"),
-            })),
-            ModuleType::LoginMt => {
-                ensure!(len == 56, "Invalid login module found!");
-                Ok(ModuleContent::Login(types::LoginModule {
-                    ctrl: u16::from_le_bytes([raw_data[0], raw_data[1]]),
-                    dst_susy: u16::from_le_bytes([raw_data[2], raw_data[3]]),
-                    dst_ser: u32::from_le_bytes([raw_data[4], raw_data[5], raw_data[6], raw_data[7]]),
-                    dst_dev: raw_data[8],
-                    dst_fkt: raw_data[9],
-                    src_susy: u16::from_le_bytes([raw_data[10], raw_data[11]]),
-                    src_ser: u32::from_le_bytes([
-                        raw_data[12],
-                        raw_data[13],
-                        raw_data[14],
-                        raw_data[15],
-                    ]),
-                    src_dev: raw_data[16],
-                    src_fkt: raw_data[17],
-                    cmd: raw_data[18],
-                    pcnt: raw_data[19],
-                    obj_num: u16::from_le_bytes([raw_data[20], raw_data[21]]),
-                    dat_len: u16::from_le_bytes([raw_data[22], raw_data[23]]),
-                    p0: u32::from_le_bytes([raw_data[24], raw_data[25], raw_data[26], raw_data[27]]),
-                    p1: u32::from_le_bytes([raw_data[28], raw_data[29], raw_data[30], raw_data[31]]),
-                    p2: u32::from_le_bytes([raw_data[32], raw_data[33], raw_data[34], raw_data[35]]),
-                    p3: u32::from_le_bytes([raw_data[36], raw_data[37], raw_data[38], raw_data[39]]),
-                    password: String::from_utf8(raw_data[40..52].to_vec())
-                        .expect("Found login module, but failed to convert password to utf8"),
-                    mode: u32::from_le_bytes([raw_data[52], raw_data[53], raw_data[54], raw_data[55]]),
-                }))
+            let available = self
+                .fill(pos + 16)
+                .map_err(|e| read_error(e, self.offset + pos))?;
+            if available < pos + 16 {
+                // Not enough data left for another module
+                pos = self.lookahead.len();
+                break false;
             }
-            ModuleType::FwChkMt => {
-                ensure!(len == 60, "Invalid firmware check module found!");
-                Ok(ModuleContent::FwChk(types::FwChkModule {
-                    ctrl: u16::from_le_bytes([raw_data[0], raw_data[1]]),
-                    dst_susy: u16::from_le_bytes([raw_data[2], raw_data[3]]),
-                    dst_ser: u32::from_le_bytes([raw_data[4], raw_data[5], raw_data[6], raw_data[7]]),
-                    dst_dev: raw_data[8],
-                    dst_fkt: raw_data[9],
-                    src_susy: u16::from_le_bytes([raw_data[10], raw_data[11]]),
-                    src_ser: u32::from_le_bytes([
-                        raw_data[12],
-                        raw_data[13],
-                        raw_data[14],
-                        raw_data[15],
-                    ]),
-                    src_dev: raw_data[16],
-                    src_fkt: raw_data[17],
-                    cmd: raw_data[18],
-                    pcnt: raw_data[19],
-                    obj_num: u16::from_le_bytes([raw_data[20], raw_data[21]]),
-                    dat_len: u16::from_le_bytes([raw_data[22], raw_data[23]]),
-                    p0: u32::from_le_bytes([raw_data[24], raw_data[25], raw_data[26], raw_data[27]]),
-                    blk_first: u32::from_le_bytes([
-                        raw_data[28],
-                        raw_data[29],
-                        raw_data[30],
-                        raw_data[31],
-                    ]),
-                    blk_last: u32::from_le_bytes([
-                        raw_data[32],
-                        raw_data[33],
-                        raw_data[34],
-                        raw_data[35],
-                    ]),
-                    cond_cnt: u16::from_le_bytes([raw_data[36], raw_data[37]]),
-                    crc: u16::from_le_bytes([raw_data[38], raw_data[39]]),
-                    adler32: u32::from_le_bytes([
-                        raw_data[40],
-                        raw_data[41],
-                        raw_data[42],
-                        raw_data[43],
-                    ]),
-                    md4: raw_data[44..60].try_into().unwrap(),
-                }))
+            let mut buf = [0; 16];
+            buf.copy_from_slice(&self.lookahead[pos..pos + 16]);
+            let header = parse_module_header(&buf);
+            let len = header.len as usize;
+            let plausible = plausible_header(
+                &header,
+                self.offset + pos,
+                &self.config.options,
+                &self.config.decoders,
+            ) && len <= budget;
+            if let Some(end) = (pos + 16).checked_add(len).filter(|_| plausible) {
+                let available = self
+                    .fill(end)
+                    .map_err(|e| read_error(e, self.offset + pos))?;
+                if available >= end {
+                    budget -= len;
+                    if checksum_matches(&header, &self.lookahead[pos + 16..end]) {
+                        break true;
+                    }
+                }
             }
-            ModuleType::CondChkMt => {
-                ensure!(len == 52, "Invalid condition check module found!");
-                Ok(ModuleContent::CondChk(types::CondChkModule {
-                    ctrl: u16::from_le_bytes([raw_data[0], raw_data[1]]),
-                    dst_susy: u16::from_le_bytes([raw_data[2], raw_data[3]]),
-                    dst_ser: u32::from_le_bytes([raw_data[4], raw_data[5], raw_data[6], raw_data[7]]),
-                    dst_dev: raw_data[8],
-                    dst_fkt: raw_data[9],
-                    src_susy: u16::from_le_bytes([raw_data[10], raw_data[11]]),
-                    src_ser: u32::from_le_bytes([
-                        raw_data[12],
-                        raw_data[13],
-                        raw_data[14],
-                        raw_data[15],
-                    ]),
-                    src_dev: raw_data[16],
-                    src_kkt: raw_data[17],
-                    cmd: raw_data[18],
-                    pcnt: raw_data[19],
-                    obj_num: u16::from_le_bytes([raw_data[20], raw_data[21]]),
-                    dat_len: u16::from_le_bytes([raw_data[22], raw_data[23]]),
-                    p0: u32::from_le_bytes([raw_data[24], raw_data[25], raw_data[26], raw_data[27]]),
-                    obj_nr: u16::from_le_bytes([raw_data[28], raw_data[29]]),
-                    rec_dw_first: u16::from_le_bytes([raw_data[30], raw_data[31]]),
-                    idx_first: u32::from_le_bytes([
-                        raw_data[32],
-                        raw_data[33],
-                        raw_data[34],
-                        raw_data[35],
-                    ]),
-                    bitmask: u32::from_le_bytes([
-                        raw_data[36],
-                        raw_data[37],
-                        raw_data[38],
-                        raw_data[39],
-                    ]),
-                    lo_bound: u32::from_le_bytes([
-                        raw_data[40],
-                        raw_data[41],
-                        raw_data[42],
-                        raw_data[43],
-                    ]),
-                    hi_bound: u32::from_le_bytes([
-                        raw_data[44],
-                        raw_data[45],
-                        raw_data[46],
-                        raw_data[47],
-                    ]),
-                    no_obj: raw_data[48],
-                    dat_valid: raw_data[49],
-                    res_1: raw_data[50],
-                    res_2: raw_data[51],
-                }))
+            pos += 1;
+        };
+        self.consume(pos);
+        self.skipped.push(start..self.offset);
+        Ok(found)
+    }
+}
+
+/// Decodes the body of a module without copying any of its variable length data
+///
+/// `module_index` and `offset` are only used to describe the module in errors
+pub fn parse_module_body<'a>(
+    raw_data: &'a [u8],
+    module_type: &ModuleType,
+    module_index: usize,
+    offset: usize,
+) -> Result<ModuleContentRef<'a>> {
+    let len = raw_data.len();
+    let bad_len = || Up2Error::BadModuleLength {
+        module_index,
+        module_type: *module_type,
+        offset,
+        len,
+    };
+    let ensure_len = |expected: usize| {
+        if len == expected {
+            Ok(())
+        } else {
+            Err(bad_len())
+        }
+    };
+    let invalid_utf8 = |_| Up2Error::InvalidUtf8 {
+        module_index,
+        module_type: *module_type,
+        offset,
+    };
+    // Command modules start with the SMA Data header
+    let data_header = || {
+        raw_data
+            .first_chunk()
+            .map(SmaDataHeader::decode)
+            .ok_or_else(bad_len)
+    };
+    match module_type {
+        ModuleType::LevelStartMt => {
+            ensure_len(4)?;
+            Ok(ModuleContentRef::LevelStart(types::LevelStartModule {
+                label: u32::from_le_bytes([raw_data[0], raw_data[1], raw_data[2], raw_data[3]]),
+            }))
+        }
+        ModuleType::LevelEndMt => {
+            ensure_len(4)?;
+            Ok(ModuleContentRef::LevelEnd(types::LevelEndModule {
+                label: u32::from_le_bytes([raw_data[0], raw_data[1], raw_data[2], raw_data[3]]),
+            }))
+        }
+        ModuleType::PauseMt => {
+            ensure_len(4)?;
+            Ok(ModuleContentRef::Pause(types::PauseModule {
+                delay: u32::from_le_bytes([raw_data[0], raw_data[1], raw_data[2], raw_data[3]]),
+            }))
+        }
+        ModuleType::LoopStartMt => {
+            ensure_len(4)?;
+            Ok(ModuleContentRef::LoopStart(types::LoopStartModule {
+                label: u32::from_le_bytes([raw_data[0], raw_data[1], raw_data[2], raw_data[3]]),
+            }))
+        }
+        ModuleType::LoopEndMt => {
+            ensure_len(8)?;
+            Ok(ModuleContentRef::LoopEnd(types::LoopEndModule {
+                label: u32::from_le_bytes([raw_data[0], raw_data[1], raw_data[2], raw_data[3]]),
+                loops: u32::from_le_bytes([raw_data[4], raw_data[5], raw_data[6], raw_data[7]]),
+            }))
+        }
+        ModuleType::FirmwareverMt => {
+            ensure_len(4)?;
+            Ok(ModuleContentRef::Firmwarever(types::FirmwareverModule {
+                major_version: raw_data[0],
+                minor_version: raw_data[1],
+                build_number: raw_data[2],
+                rev: raw_data[3],
+            }))
+        }
+        ModuleType::TextMt => Ok(ModuleContentRef::Text(types::TextModuleRef {
+            data: core::str::from_utf8(raw_data).map_err(invalid_utf8)?,
+        })),
+        ModuleType::LoginMt => {
+            ensure_len(56)?;
+            Ok(ModuleContentRef::Login(types::LoginModule {
+                data_header: data_header()?,
+                p1: u32::from_le_bytes([raw_data[28], raw_data[29], raw_data[30], raw_data[31]]),
+                p2: u32::from_le_bytes([raw_data[32], raw_data[33], raw_data[34], raw_data[35]]),
+                p3: u32::from_le_bytes([raw_data[36], raw_data[37], raw_data[38], raw_data[39]]),
+                password: raw_data[40..52].try_into().unwrap(),
+                mode: u32::from_le_bytes([raw_data[52], raw_data[53], raw_data[54], raw_data[55]]),
+            }))
+        }
+        ModuleType::FwChkMt => {
+            ensure_len(60)?;
+            Ok(ModuleContentRef::FwChk(types::FwChkModule {
+                data_header: data_header()?,
+                blk_first: u32::from_le_bytes([
+                    raw_data[28],
+                    raw_data[29],
+                    raw_data[30],
+                    raw_data[31],
+                ]),
+                blk_last: u32::from_le_bytes([
+                    raw_data[32],
+                    raw_data[33],
+                    raw_data[34],
+                    raw_data[35],
+                ]),
+                cond_cnt: u16::from_le_bytes([raw_data[36], raw_data[37]]),
+                crc: u16::from_le_bytes([raw_data[38], raw_data[39]]),
+                adler32: u32::from_le_bytes([
+                    raw_data[40],
+                    raw_data[41],
+                    raw_data[42],
+                    raw_data[43],
+                ]),
+                md4: raw_data[44..60].try_into().unwrap(),
+            }))
+        }
+        ModuleType::CondChkMt => {
+            ensure_len(52)?;
+            Ok(ModuleContentRef::CondChk(types::CondChkModule {
+                data_header: data_header()?,
+                obj_nr: u16::from_le_bytes([raw_data[28], raw_data[29]]),
+                rec_dw_first: u16::from_le_bytes([raw_data[30], raw_data[31]]),
+                idx_first: u32::from_le_bytes([
+                    raw_data[32],
+                    raw_data[33],
+                    raw_data[34],
+                    raw_data[35],
+                ]),
+                bitmask: u32::from_le_bytes([
+                    raw_data[36],
+                    raw_data[37],
+                    raw_data[38],
+                    raw_data[39],
+                ]),
+                lo_bound: u32::from_le_bytes([
+                    raw_data[40],
+                    raw_data[41],
+                    raw_data[42],
+                    raw_data[43],
+                ]),
+                hi_bound: u32::from_le_bytes([
+                    raw_data[44],
+                    raw_data[45],
+                    raw_data[46],
+                    raw_data[47],
+                ]),
+                no_obj: raw_data[48],
+                dat_valid: raw_data[49],
+                res_1: raw_data[50],
+                res_2: raw_data[51],
+            }))
+        }
+        ModuleType::FirmwareMt => {
+            if len < 32 {
+                return Err(bad_len());
             }
-            ModuleType::FirmwareMt => Ok(ModuleContent::Firmware(types::FirmwareModule {
-                ctrl: u16::from_le_bytes([raw_data[0], raw_data[1]]),
-                dst_susy: u16::from_le_bytes([raw_data[2], raw_data[3]]),
-                dst_ser: u32::from_le_bytes([raw_data[4], raw_data[5], raw_data[6], raw_data[7]]),
-                dst_dev: raw_data[8],
-                dst_fkt: raw_data[9],
-                src_susy: u16::from_le_bytes([raw_data[10], raw_data[11]]),
-                src_ser: u32::from_le_bytes([raw_data[12], raw_data[13], raw_data[14], raw_data[15]]),
-                src_dev: raw_data[16],
-                src_fkt: raw_data[17],
-                cmd: raw_data[18],
-                pcnt: raw_data[19],
-                obj_num: u16::from_le_bytes([raw_data[20], raw_data[21]]),
-                dat_len: u16::from_le_bytes([raw_data[22], raw_data[23]]),
-                p0: u32::from_le_bytes([raw_data[24], raw_data[25], raw_data[26], raw_data[27]]),
+            Ok(ModuleContentRef::Firmware(types::FirmwareModuleRef {
+                data_header: data_header()?,
                 delay: u32::from_le_bytes([raw_data[28], raw_data[29], raw_data[30], raw_data[31]]),
-                data: raw_data[32..].to_vec(),
-            })),
-            ModuleType::LogoutMt => {
-                ensure!(len == 28, "Invalid logout module found!");
-                Ok(ModuleContent::Logout(types::LogoutModule {
-                    ctrl: u16::from_le_bytes([raw_data[0], raw_data[1]]),
-                    dst_susy: u16::from_le_bytes([raw_data[2], raw_data[3]]),
-                    dst_ser: u32::from_le_bytes([raw_data[4], raw_data[5], raw_data[6], raw_data[7]]),
-                    dst_dev: raw_data[8],
-                    dst_fkt: raw_data[9],
-                    src_susy: u16::from_le_bytes([raw_data[10], raw_data[11]]),
-                    src_ser: u32::from_le_bytes([
-                        raw_data[12],
-                        raw_data[13],
-                        raw_data[14],
-                        raw_data[15],
-                    ]),
-                    src_dev: raw_data[16],
-                    src_fkt: raw_data[17],
-                    cmd: raw_data[18],
-                    pcnt: raw_data[19],
-                    obj_num: u16::from_le_bytes([raw_data[20], raw_data[21]]),
-                    dat_len: u16::from_le_bytes([raw_data[22], raw_data[23]]),
-                    p0: u32::from_le_bytes([raw_data[24], raw_data[25], raw_data[26], raw_data[27]]),
-                }))
-            }
-            ModuleType::UpFmt10Mt => Ok(ModuleContent::UpFmt10(types::UpFmt10Module {
-                data: raw_data.to_vec(),
-            })),
-            ModuleType::UnknownMt => Ok(ModuleContent::Unknown(types::UnknownModule {
-                data: raw_data.to_vec(),
-            })),
+                data: &raw_data[32..],
+            }))
+        }
+        ModuleType::LogoutMt => {
+            ensure_len(28)?;
+            Ok(ModuleContentRef::Logout(types::LogoutModule {
+                data_header: data_header()?,
+            }))
         }
+        ModuleType::UpFmt10Mt => Ok(ModuleContentRef::UpFmt10(types::UpFmt10ModuleRef {
+            data: raw_data,
+        })),
+        ModuleType::Unknown(id) => Ok(ModuleContentRef::Unknown(types::UnknownModuleRef {
+            module_type: *id,
+            data: raw_data,
+        })),
     }
 }
 
-impl Iterator for Up2Parser {
+impl<R: Up2Read> Iterator for Up2Parser<R> {
     type Item = Result<Module>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let bytes = &mut self.reader.borrow_mut();
-        let mut buf = [0; 16];
-        match bytes.read_exact(&mut buf) {
-            Ok(_) => {
-                let mut module = Module {
-                    header: super::types::ModuleHeader {
-                        adler: 0,
-                        module_type: 0,
-                        susyid: 0,
-                        len: 0,
-                    },
-                    content: ModuleContent::Unknown(super::types::UnknownModule { data: vec![] }),
-                };
-            
-                // Copy the module header bytes into the module struct
-                module.header.adler = u32::from_le_bytes([
-                    buf[0],
-                    buf[1],
-                    buf[2],
-                    buf[3],
-                ]);
-                module.header.module_type = u32::from_le_bytes([
-                    buf[4],
-                    buf[5],
-                    buf[6],
-                    buf[7],
-                ]);
-                module.header.susyid = u32::from_le_bytes([
-                    buf[8],
-                    buf[9],
-                    buf[10],
-                    buf[11],
-                ]);
-                module.header.len = u32::from_le_bytes([
-                    buf[12],
-                    buf[13],
-                    buf[14],
-                    buf[15],
-                ]);
-                // Copy the module content bytes into the module struct
-                let module_type: ModuleType = module.header.module_type.into();
-                let module_len = module.header.len;
-                let mut buf = vec![0; module_len as usize];
-                bytes.read_exact(&mut buf).unwrap();
-                match self.parse_module_body(&buf, &module_type) {
-                    Ok(content) => module.content = content,
-                    Err(e) => return Some(Err(e)),
+        if self.finished {
+            return None;
+        }
+        let result = self.next_module();
+        if let Some(Err(e)) = &result {
+            // The reader is somewhere in the middle of a module and will most likely fail again
+            if e.is_read_error() {
+                self.finished = true;
+            }
+        }
+        result
+    }
+}
+
+impl<R: Up2Read> Up2Parser<R> {
+    fn next_module(&mut self) -> Option<Result<Module>> {
+        let mut warnings = vec![];
+        if self.resync {
+            self.resync = false;
+            match self.resynchronize() {
+                Ok(found) => {
+                    let skipped = self.skipped[self.skipped.len() - 1].clone();
+                    if !found {
+                        return None;
+                    }
+                    warnings.push(ModuleWarning::SkippedBytes {
+                        start: skipped.start,
+                        end: skipped.end,
+                    });
                 }
-                self.offset += 16 + module_len as usize;
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        let module_index = self.module_index;
+        let offset = self.offset;
+        match self.fill(16) {
+            // End of file
+            Ok(0) => return None,
+            Ok(available) if available < 16 => {
+                self.consume(available);
+                return Some(Err(Up2Error::TruncatedHeader {
+                    module_index: Some(module_index),
+                    offset,
+                }));
+            }
+            Ok(_) => {}
+            Err(e) => return Some(Err(e.in_module(module_index, None, offset))),
+        }
+        self.module_index += 1;
+
+        let mut buf = [0; 16];
+        buf.copy_from_slice(&self.lookahead[..16]);
+        let header = parse_module_header(&buf);
+        match self.read_module(header, module_index) {
+            Ok(mut module) => {
+                self.consume(16 + header.len as usize);
+                warnings.append(&mut module.warnings);
+                module.warnings = warnings;
                 Some(Ok(module))
             }
-            Err(_) => None,
+            Err(e) => {
+                if self.config.options.recover {
+                    // Keep the bytes of the broken module, the next module might start inside of it
+                    self.resync = true;
+                } else {
+                    match e {
+                        Up2Error::ModuleTooLarge { .. } | Up2Error::FileTooLarge { .. } => {
+                            self.finished = true
+                        }
+                        _ => {
+                            let len = self.lookahead.len().min(16 + header.len as usize);
+                            self.consume(len);
+                        }
+                    }
+                }
+                Some(Err(e))
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Reads into `buf` until it is full or the reader reaches end of file, returning the number of bytes read
+pub(crate) fn read_full<R: Up2Read + ?Sized>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
+
+/// Appends up to `len` bytes to `buf`, growing it only as data actually arrives
+///
+/// This keeps a module header with a bogus length from allocating memory for data that is not there.
+pub(crate) fn read_append<R: Up2Read + ?Sized>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    len: usize,
+) -> Result<()> {
+    const CHUNK_SIZE: usize = 64 * 1024;
+    let end = buf.len() + len;
+    while buf.len() < end {
+        let start = buf.len();
+        let chunk = (end - start).min(CHUNK_SIZE);
+        buf.resize(start + chunk, 0);
+        let read = read_full(reader, &mut buf[start..])?;
+        buf.truncate(start + read);
+        if read < chunk {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use super::error::Result;
+
+/// Source of up2 data for [`Up2Parser`](super::parse::Up2Parser)
+///
+/// Implemented for byte slices, other sources have to implement it themselves. With the `std`
+/// feature any `std::io::Read` can be used by wrapping it in an [`IoReader`].
+pub trait Up2Read {
+    /// Reads into `buf` and returns the number of bytes read, 0 means end of file
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+}
+
+impl Up2Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = buf.len().min(self.len());
+        let (data, rest) = self.split_at(len);
+        buf[..len].copy_from_slice(data);
+        *self = rest;
+        Ok(len)
+    }
+}
+
+impl<R: Up2Read + ?Sized> Up2Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+impl<R: Up2Read + ?Sized> Up2Read for alloc::boxed::Box<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+/// Reads up2 data from a `std::io::Read`, e.g. a file
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoReader<R> {
+    inner: R,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> IoReader<R> {
+    pub fn new(inner: R) -> Self {
+        IoReader { inner }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> Up2Read for IoReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        loop {
+            match self.inner.read(buf) {
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                result => return Ok(result?),
+            }
+        }
+    }
+}
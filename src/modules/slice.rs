@@ -0,0 +1,173 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use super::config::ParserConfig;
+use super::error::{Result, Up2Error};
+use super::parse::{
+    check_header, check_limits, checksum_matches, parse_module, parse_module_header,
+    plausible_header, resync_budget,
+};
+use super::types::{ModuleHeader, ModuleRef, ModuleType, ModuleWarning, Up2Header};
+
+/// Parses an up2 file that is already in memory (e.g. a memory-mapped file) without copying it
+///
+/// The yielded modules borrow their variable length data (text, firmware data, ...) from the input.
+pub struct Up2Slice<'a> {
+    data: &'a [u8],
+    offset: usize,
+    module_index: usize,
+    config: ParserConfig,
+    /// Set after an error in recovery mode, the next call searches for the next module
+    resync: bool,
+    skipped: Vec<Range<usize>>,
+    pub header: Up2Header,
+}
+
+impl<'a> Up2Slice<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        let buf = data.first_chunk::<8>().ok_or(Up2Error::TruncatedHeader {
+            module_index: None,
+            offset: 0,
+        })?;
+        let header = check_header(buf)?;
+        Ok(Up2Slice {
+            data,
+            offset: buf.len(),
+            module_index: 0,
+            config: ParserConfig::default(),
+            resync: false,
+            skipped: vec![],
+            header,
+        })
+    }
+
+    /// Returns the absolute byte offset of the next module in the file
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Sets the options and decoders, see [`ParserConfig`]
+    pub fn with_config(mut self, config: ParserConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+impl<'a> Up2Slice<'a> {
+    /// Returns the byte ranges that were skipped in recovery mode so far
+    pub fn skipped(&self) -> &[Range<usize>] {
+        &self.skipped
+    }
+
+    fn read_module(&self, header: &ModuleHeader, module_index: usize) -> Result<ModuleRef<'a>> {
+        let offset = self.offset;
+        check_limits(&self.config.options, header, module_index, offset)?;
+        let body = &self.data[offset + 16..];
+        let Some(body) = body.get(..header.len as usize) else {
+            return Err(Up2Error::TruncatedBody {
+                module_index,
+                module_type: ModuleType::from_id(header.module_type),
+                offset,
+                expected: header.len,
+                actual: body.len(),
+            });
+        };
+        parse_module(
+            *header,
+            body,
+            module_index,
+            offset,
+            self.config.options.checksum_mode,
+            &self.config.decoders,
+        )
+    }
+
+    /// Moves to the next plausible module, returns `false` if the end of the file was reached
+    fn resynchronize(&mut self) -> bool {
+        let data = self.data;
+        let mut budget = resync_budget(&self.config.options);
+        // The module at the current position is known to be broken
+        for pos in self.offset + 1..data.len() {
+            let Some(buf) = data[pos..].first_chunk::<16>() else {
+                break;
+            };
+            let header = parse_module_header(buf);
+            let len = header.len as usize;
+            if !plausible_header(&header, pos, &self.config.options, &self.config.decoders)
+                || len > budget
+            {
+                continue;
+            }
+            if let Some(body) = data[pos + 16..].get(..len) {
+                budget -= len;
+                if checksum_matches(&header, body) {
+                    self.offset = pos;
+                    return true;
+                }
+            }
+        }
+        self.offset = data.len();
+        false
+    }
+}
+
+impl<'a> Iterator for Up2Slice<'a> {
+    type Item = Result<ModuleRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut warnings = vec![];
+        if self.resync {
+            self.resync = false;
+            let start = self.offset;
+            let found = self.resynchronize();
+            self.skipped.push(start..self.offset);
+            if !found {
+                return None;
+            }
+            warnings.push(ModuleWarning::SkippedBytes {
+                start,
+                end: self.offset,
+            });
+        }
+
+        let module_index = self.module_index;
+        let offset = self.offset;
+        let remaining = &self.data[offset..];
+        if remaining.is_empty() {
+            return None;
+        }
+        let Some(buf) = remaining.first_chunk::<16>() else {
+            self.offset = self.data.len();
+            return Some(Err(Up2Error::TruncatedHeader {
+                module_index: Some(module_index),
+                offset,
+            }));
+        };
+        self.module_index += 1;
+
+        let header = parse_module_header(buf);
+        match self.read_module(&header, module_index) {
+            Ok(mut module) => {
+                self.offset += 16 + module.span.len;
+                warnings.append(&mut module.warnings);
+                module.warnings = warnings;
+                Some(Ok(module))
+            }
+            Err(e) => {
+                if self.config.options.recover {
+                    // The next module might start inside of the broken one
+                    self.resync = true;
+                } else {
+                    match e {
+                        Up2Error::ModuleTooLarge { .. }
+                        | Up2Error::FileTooLarge { .. }
+                        | Up2Error::TruncatedBody { .. } => self.offset = self.data.len(),
+                        _ => self.offset += 16 + header.len as usize,
+                    }
+                }
+                Some(Err(e))
+            }
+        }
+    }
+}
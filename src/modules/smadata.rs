@@ -0,0 +1,320 @@
+use core::fmt;
+
+use super::types::SmaDataHeader;
+
+// The meaning of these fields comes from reverse-engineering notes and the public SMA Data
+// protocol documentation, values that are not known are kept as raw numbers.
+
+/// The `ctrl` word, packet length in the low byte and flags in the high byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ctrl(pub u16);
+
+impl Ctrl {
+    /// Set for requests that are sent to a group of devices instead of a single one
+    pub const GROUP: u8 = 0x80;
+    /// Set for responses
+    pub const RESPONSE: u8 = 0x40;
+
+    /// Length of the packet in 32 bit words
+    pub fn len_words(self) -> u8 {
+        self.0 as u8
+    }
+
+    pub fn flags(self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    pub fn is_group(self) -> bool {
+        self.flags() & Self::GROUP != 0
+    }
+
+    pub fn is_response(self) -> bool {
+        self.flags() & Self::RESPONSE != 0
+    }
+}
+
+impl fmt::Display for Ctrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} words, flags 0x{:02x}",
+            self.len_words(),
+            self.flags()
+        )?;
+        // The known flags by name and the remaining bits as a number, in parentheses
+        let mut first = true;
+        let mut item = |f: &mut fmt::Formatter<'_>, args: fmt::Arguments<'_>| {
+            f.write_str(if first { " (" } else { ", " })?;
+            first = false;
+            f.write_fmt(args)
+        };
+        for (bit, name) in [(Self::GROUP, "group"), (Self::RESPONSE, "response")] {
+            if self.flags() & bit != 0 {
+                item(f, format_args!("{}", name))?;
+            }
+        }
+        let unknown = self.flags() & !(Self::GROUP | Self::RESPONSE);
+        if unknown != 0 {
+            item(f, format_args!("0x{:02x}", unknown))?;
+        }
+        if !first {
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
+
+/// Command codes of the SMA Data protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    GetNet,
+    SearchDev,
+    CfgNetAdr,
+    SetGrpAdr,
+    DelGrpAdr,
+    GetNetStart,
+    GetCInfo,
+    SynOnline,
+    GetData,
+    SetData,
+    GetSInfo,
+    SetMPara,
+    GetBInfo,
+    GetBin,
+    SetBin,
+    TnrVerify,
+    VarValue,
+    VarFind,
+    VarStatusOut,
+    VarDefVal,
+    VarStatusIn,
+    PdeLimit,
+    TeamFunction,
+    /// A command code that is not known, with its raw value
+    Other(u8),
+}
+
+impl Command {
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            1 => Command::GetNet,
+            2 => Command::SearchDev,
+            3 => Command::CfgNetAdr,
+            4 => Command::SetGrpAdr,
+            5 => Command::DelGrpAdr,
+            6 => Command::GetNetStart,
+            9 => Command::GetCInfo,
+            10 => Command::SynOnline,
+            11 => Command::GetData,
+            12 => Command::SetData,
+            13 => Command::GetSInfo,
+            15 => Command::SetMPara,
+            20 => Command::GetBInfo,
+            21 => Command::GetBin,
+            22 => Command::SetBin,
+            30 => Command::TnrVerify,
+            40 => Command::VarValue,
+            41 => Command::VarFind,
+            44 => Command::VarStatusOut,
+            50 => Command::VarDefVal,
+            51 => Command::VarStatusIn,
+            60 => Command::PdeLimit,
+            61 => Command::TeamFunction,
+            _ => Command::Other(code),
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        match self {
+            Command::GetNet => 1,
+            Command::SearchDev => 2,
+            Command::CfgNetAdr => 3,
+            Command::SetGrpAdr => 4,
+            Command::DelGrpAdr => 5,
+            Command::GetNetStart => 6,
+            Command::GetCInfo => 9,
+            Command::SynOnline => 10,
+            Command::GetData => 11,
+            Command::SetData => 12,
+            Command::GetSInfo => 13,
+            Command::SetMPara => 15,
+            Command::GetBInfo => 20,
+            Command::GetBin => 21,
+            Command::SetBin => 22,
+            Command::TnrVerify => 30,
+            Command::VarValue => 40,
+            Command::VarFind => 41,
+            Command::VarStatusOut => 44,
+            Command::VarDefVal => 50,
+            Command::VarStatusIn => 51,
+            Command::PdeLimit => 60,
+            Command::TeamFunction => 61,
+            Command::Other(code) => code,
+        }
+    }
+
+    /// Returns the name from the protocol documentation, `None` for unknown commands
+    pub fn name(self) -> Option<&'static str> {
+        Some(match self {
+            Command::GetNet => "CMD_GET_NET",
+            Command::SearchDev => "CMD_SEARCH_DEV",
+            Command::CfgNetAdr => "CMD_CFG_NETADR",
+            Command::SetGrpAdr => "CMD_SET_GRPADR",
+            Command::DelGrpAdr => "CMD_DEL_GRPADR",
+            Command::GetNetStart => "CMD_GET_NET_START",
+            Command::GetCInfo => "CMD_GET_CINFO",
+            Command::SynOnline => "CMD_SYN_ONLINE",
+            Command::GetData => "CMD_GET_DATA",
+            Command::SetData => "CMD_SET_DATA",
+            Command::GetSInfo => "CMD_GET_SINFO",
+            Command::SetMPara => "CMD_SET_MPARA",
+            Command::GetBInfo => "CMD_GET_BINFO",
+            Command::GetBin => "CMD_GET_BIN",
+            Command::SetBin => "CMD_SET_BIN",
+            Command::TnrVerify => "CMD_TNR_VERIFY",
+            Command::VarValue => "CMD_VAR_VALUE",
+            Command::VarFind => "CMD_VAR_FIND",
+            Command::VarStatusOut => "CMD_VAR_STATUS_OUT",
+            Command::VarDefVal => "CMD_VAR_DEFVAL",
+            Command::VarStatusIn => "CMD_VAR_STATUS_IN",
+            Command::PdeLimit => "CMD_PDELIMIT",
+            Command::TeamFunction => "CMD_TEAM_FUNCTION",
+            Command::Other(_) => return None,
+        })
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} (0x{:02x})", name, self.code()),
+            None => write!(f, "0x{:02x}", self.code()),
+        }
+    }
+}
+
+/// The `pcnt` byte, the number of the packet in the lower 7 bits and the first fragment flag in the top bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketCounter(pub u8);
+
+impl PacketCounter {
+    pub fn count(self) -> u8 {
+        self.0 & 0x7f
+    }
+
+    pub fn is_first_fragment(self) -> bool {
+        self.0 & 0x80 != 0
+    }
+}
+
+impl fmt::Display for PacketCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.count())?;
+        if self.is_first_fragment() {
+            write!(f, ", first fragment")?;
+        }
+        Ok(())
+    }
+}
+
+/// The devices a packet is addressed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// Every device, SUSy-ID 0xffff and serial number 0xffffffff
+    Broadcast,
+    /// Every device with this SUSy-ID, serial number 0xffffffff
+    AnyOf { susy: u16 },
+    /// The device with this serial number, `susy` is `None` if the SUSy-ID is 0xffff
+    Device { susy: Option<u16>, serial: u32 },
+}
+
+impl Target {
+    pub const ANY_SUSY: u16 = 0xffff;
+    pub const ANY_SERIAL: u32 = 0xffffffff;
+
+    pub fn from_address(susy: u16, serial: u32) -> Self {
+        match (susy, serial) {
+            (Self::ANY_SUSY, Self::ANY_SERIAL) => Target::Broadcast,
+            (susy, Self::ANY_SERIAL) => Target::AnyOf { susy },
+            (Self::ANY_SUSY, serial) => Target::Device { susy: None, serial },
+            (susy, serial) => Target::Device {
+                susy: Some(susy),
+                serial,
+            },
+        }
+    }
+
+    /// Returns the raw SUSy-ID and serial number
+    pub fn address(self) -> (u16, u32) {
+        match self {
+            Target::Broadcast => (Self::ANY_SUSY, Self::ANY_SERIAL),
+            Target::AnyOf { susy } => (susy, Self::ANY_SERIAL),
+            Target::Device { susy, serial } => (susy.unwrap_or(Self::ANY_SUSY), serial),
+        }
+    }
+
+    /// Checks if a device with the given SUSy-ID and serial number is addressed
+    pub fn matches(self, susy: u16, serial: u32) -> bool {
+        match self {
+            Target::Broadcast => true,
+            Target::AnyOf { susy: s } => s == susy,
+            Target::Device { susy: s, serial: n } => n == serial && s.is_none_or(|s| s == susy),
+        }
+    }
+}
+
+// SMA shows serial numbers in decimal and SUSy-IDs as decimal numbers as well
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Broadcast => write!(f, "all devices"),
+            Target::AnyOf { susy } => write!(f, "all devices with SUSy-ID {}", susy),
+            Target::Device {
+                susy: Some(susy),
+                serial,
+            } => write!(f, "SUSy-ID {} SN {}", susy, serial),
+            Target::Device { susy: None, serial } => write!(f, "SN {}", serial),
+        }
+    }
+}
+
+impl SmaDataHeader {
+    /// Returns the devices the module is sent to
+    pub fn target(&self) -> Target {
+        Target::from_address(self.dst_susy, self.dst_ser)
+    }
+
+    pub fn ctrl(&self) -> Ctrl {
+        Ctrl(self.ctrl)
+    }
+
+    pub fn command(&self) -> Command {
+        Command::from_code(self.cmd)
+    }
+
+    pub fn packet_counter(&self) -> PacketCounter {
+        PacketCounter(self.pcnt)
+    }
+}
+
+impl fmt::Display for SmaDataHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ctrl: {}, cmd: {}, pcnt: {}, dst: {} dev 0x{:02x} fkt 0x{:02x}, \
+             src: {} dev 0x{:02x} fkt 0x{:02x}, obj_num: 0x{:04x}, dat_len: {}, p0: 0x{:08x}",
+            self.ctrl(),
+            self.command(),
+            self.packet_counter(),
+            self.target(),
+            self.dst_dev,
+            self.dst_fkt,
+            Target::from_address(self.src_susy, self.src_ser),
+            self.src_dev,
+            self.src_fkt,
+            self.obj_num,
+            self.dat_len,
+            self.p0
+        )
+    }
+}
@@ -0,0 +1,253 @@
+use alloc::vec::Vec;
+
+use super::types::{
+    CondChkModule, FirmwareModule, FirmwareverModule, FwChkModule, LevelEndModule,
+    LevelStartModule, LoginModule, LogoutModule, LoopEndModule, LoopStartModule, Module,
+    ModuleContent, PauseModule,
+};
+
+/// Position of a module in the up2 file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ModuleSpan {
+    /// Absolute offset of the 16 byte module header
+    pub header_offset: usize,
+    /// Absolute offset of the module body, directly after the header
+    pub body_offset: usize,
+    /// Length of the module body
+    pub len: usize,
+}
+
+/// Position of a single field, relative to the start of the module body
+///
+/// Used for the field layouts of the module types, see [`ModuleContent::fields`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSpan {
+    pub name: &'static str,
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// Position of a single field of a parsed module, as an absolute offset in the file
+///
+/// Returned by [`Module::field_spans`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFieldSpan {
+    pub name: &'static str,
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl FieldSpan {
+    /// Returns the position of the field in the file, for a module with the given span
+    pub fn in_file(&self, span: &ModuleSpan) -> FileFieldSpan {
+        FileFieldSpan {
+            name: self.name,
+            offset: span.body_offset + self.offset,
+            len: self.len,
+        }
+    }
+}
+
+const fn field(name: &'static str, offset: usize, len: usize) -> FieldSpan {
+    FieldSpan { name, offset, len }
+}
+
+impl LevelStartModule {
+    pub const FIELDS: &'static [FieldSpan] = &[field("label", 0, 4)];
+}
+
+impl LevelEndModule {
+    pub const FIELDS: &'static [FieldSpan] = &[field("label", 0, 4)];
+}
+
+impl PauseModule {
+    pub const FIELDS: &'static [FieldSpan] = &[field("delay", 0, 4)];
+}
+
+impl LoopStartModule {
+    pub const FIELDS: &'static [FieldSpan] = &[field("label", 0, 4)];
+}
+
+impl LoopEndModule {
+    pub const FIELDS: &'static [FieldSpan] = &[field("label", 0, 4), field("loops", 4, 4)];
+}
+
+impl FirmwareverModule {
+    pub const FIELDS: &'static [FieldSpan] = &[
+        field("major_version", 0, 1),
+        field("minor_version", 1, 1),
+        field("build_number", 2, 1),
+        field("rev", 3, 1),
+    ];
+}
+
+impl LoginModule {
+    pub const FIELDS: &'static [FieldSpan] = &[
+        field("ctrl", 0, 2),
+        field("dst_susy", 2, 2),
+        field("dst_ser", 4, 4),
+        field("dst_dev", 8, 1),
+        field("dst_fkt", 9, 1),
+        field("src_susy", 10, 2),
+        field("src_ser", 12, 4),
+        field("src_dev", 16, 1),
+        field("src_fkt", 17, 1),
+        field("cmd", 18, 1),
+        field("pcnt", 19, 1),
+        field("obj_num", 20, 2),
+        field("dat_len", 22, 2),
+        field("p0", 24, 4),
+        field("p1", 28, 4),
+        field("p2", 32, 4),
+        field("p3", 36, 4),
+        field("password", 40, 12),
+        field("mode", 52, 4),
+    ];
+}
+
+impl FwChkModule {
+    pub const FIELDS: &'static [FieldSpan] = &[
+        field("ctrl", 0, 2),
+        field("dst_susy", 2, 2),
+        field("dst_ser", 4, 4),
+        field("dst_dev", 8, 1),
+        field("dst_fkt", 9, 1),
+        field("src_susy", 10, 2),
+        field("src_ser", 12, 4),
+        field("src_dev", 16, 1),
+        field("src_fkt", 17, 1),
+        field("cmd", 18, 1),
+        field("pcnt", 19, 1),
+        field("obj_num", 20, 2),
+        field("dat_len", 22, 2),
+        field("p0", 24, 4),
+        field("blk_first", 28, 4),
+        field("blk_last", 32, 4),
+        field("cond_cnt", 36, 2),
+        field("crc", 38, 2),
+        field("adler32", 40, 4),
+        field("md4", 44, 16),
+    ];
+}
+
+impl CondChkModule {
+    pub const FIELDS: &'static [FieldSpan] = &[
+        field("ctrl", 0, 2),
+        field("dst_susy", 2, 2),
+        field("dst_ser", 4, 4),
+        field("dst_dev", 8, 1),
+        field("dst_fkt", 9, 1),
+        field("src_susy", 10, 2),
+        field("src_ser", 12, 4),
+        field("src_dev", 16, 1),
+        field("src_fkt", 17, 1),
+        field("cmd", 18, 1),
+        field("pcnt", 19, 1),
+        field("obj_num", 20, 2),
+        field("dat_len", 22, 2),
+        field("p0", 24, 4),
+        field("obj_nr", 28, 2),
+        field("rec_dw_first", 30, 2),
+        field("idx_first", 32, 4),
+        field("bitmask", 36, 4),
+        field("lo_bound", 40, 4),
+        field("hi_bound", 44, 4),
+        field("no_obj", 48, 1),
+        field("dat_valid", 49, 1),
+        field("res_1", 50, 1),
+        field("res_2", 51, 1),
+    ];
+}
+
+impl FirmwareModule {
+    /// The fixed part of the module, the firmware data follows after `delay` until the end of the body
+    pub const FIELDS: &'static [FieldSpan] = &[
+        field("ctrl", 0, 2),
+        field("dst_susy", 2, 2),
+        field("dst_ser", 4, 4),
+        field("dst_dev", 8, 1),
+        field("dst_fkt", 9, 1),
+        field("src_susy", 10, 2),
+        field("src_ser", 12, 4),
+        field("src_dev", 16, 1),
+        field("src_fkt", 17, 1),
+        field("cmd", 18, 1),
+        field("pcnt", 19, 1),
+        field("obj_num", 20, 2),
+        field("dat_len", 22, 2),
+        field("p0", 24, 4),
+        field("delay", 28, 4),
+    ];
+}
+
+impl LogoutModule {
+    pub const FIELDS: &'static [FieldSpan] = &[
+        field("ctrl", 0, 2),
+        field("dst_susy", 2, 2),
+        field("dst_ser", 4, 4),
+        field("dst_dev", 8, 1),
+        field("dst_fkt", 9, 1),
+        field("src_susy", 10, 2),
+        field("src_ser", 12, 4),
+        field("src_dev", 16, 1),
+        field("src_fkt", 17, 1),
+        field("cmd", 18, 1),
+        field("pcnt", 19, 1),
+        field("obj_num", 20, 2),
+        field("dat_len", 22, 2),
+        field("p0", 24, 4),
+    ];
+}
+
+impl ModuleContent {
+    /// Returns the fixed field layout of this module type, relative to the start of the body
+    ///
+    /// Modules without a fixed layout (text, UpFmt10, unknown and custom modules) return an empty slice
+    pub fn fields(&self) -> &'static [FieldSpan] {
+        match self {
+            ModuleContent::LevelStart(_) => LevelStartModule::FIELDS,
+            ModuleContent::LevelEnd(_) => LevelEndModule::FIELDS,
+            ModuleContent::Pause(_) => PauseModule::FIELDS,
+            ModuleContent::LoopStart(_) => LoopStartModule::FIELDS,
+            ModuleContent::LoopEnd(_) => LoopEndModule::FIELDS,
+            ModuleContent::Firmwarever(_) => FirmwareverModule::FIELDS,
+            ModuleContent::Login(_) => LoginModule::FIELDS,
+            ModuleContent::FwChk(_) => FwChkModule::FIELDS,
+            ModuleContent::CondChk(_) => CondChkModule::FIELDS,
+            ModuleContent::Firmware(_) => FirmwareModule::FIELDS,
+            ModuleContent::Logout(_) => LogoutModule::FIELDS,
+            ModuleContent::Text(_)
+            | ModuleContent::UpFmt10(_)
+            | ModuleContent::Unknown(_)
+            | ModuleContent::Custom(_) => &[],
+        }
+    }
+}
+
+impl Module {
+    /// Returns every field of the module with its absolute offset in the file
+    ///
+    /// Variable length data (text, firmware data, ...) is reported as a field called "data"
+    pub fn field_spans(&self) -> Vec<FileFieldSpan> {
+        let fields = self.content.fields();
+        let mut spans: Vec<FileFieldSpan> = fields.iter().map(|f| f.in_file(&self.span)).collect();
+        let fixed_len = fields.iter().map(|f| f.offset + f.len).max().unwrap_or(0);
+        match self.content {
+            ModuleContent::Text(_)
+            | ModuleContent::Firmware(_)
+            | ModuleContent::UpFmt10(_)
+            | ModuleContent::Unknown(_)
+            | ModuleContent::Custom(_) => spans.push(
+                field("data", fixed_len, self.span.len.saturating_sub(fixed_len))
+                    .in_file(&self.span),
+            ),
+            _ => {}
+        }
+        spans
+    }
+
+    /// Returns the absolute position of a single field by name
+    pub fn field_span(&self, name: &str) -> Option<FileFieldSpan> {
+        self.field_spans().into_iter().find(|f| f.name == name)
+    }
+}
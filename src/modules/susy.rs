@@ -0,0 +1,134 @@
+use alloc::borrow::Cow;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A device type that a SUSy-ID stands for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SusyEntry {
+    pub id: u32,
+    /// Product family, e.g. "Sunny Boy"
+    pub family: Cow<'static, str>,
+    /// Model name, e.g. "Sunny Boy 3.0"
+    pub model: Cow<'static, str>,
+}
+
+impl fmt::Display for SusyEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.model)
+    }
+}
+
+const fn entry(id: u32, family: &'static str, model: &'static str) -> SusyEntry {
+    SusyEntry {
+        id,
+        family: Cow::Borrowed(family),
+        model: Cow::Borrowed(model),
+    }
+}
+
+/// SUSy-IDs of the meters and the Home Manager, as they show up in their Speedwire packets
+///
+/// Inverters are missing because their ids could not be confirmed yet, they can be added with
+/// [`SusyCatalog::load_str`]. 0xffff addresses every device, see [`Target`](super::smadata::Target).
+static BUILTIN: &[SusyEntry] = &[
+    entry(0x010e, "Energy Meter", "SMA Energy Meter"),
+    entry(0x015d, "Energy Meter", "SMA Energy Meter 2.0"),
+    entry(0x0174, "Sunny Home Manager", "Sunny Home Manager 2.0"),
+];
+
+/// Looks up a SUSy-ID in the built-in table
+pub fn describe(id: u32) -> Option<&'static SusyEntry> {
+    BUILTIN.iter().find(|e| e.id == id)
+}
+
+/// The built-in table with extra entries, e.g. loaded from a file
+///
+/// Extra entries take precedence over built-in entries with the same id.
+#[derive(Debug, Clone, Default)]
+pub struct SusyCatalog {
+    extra: Vec<SusyEntry>,
+}
+
+impl SusyCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn describe(&self, id: u32) -> Option<&SusyEntry> {
+        self.extra
+            .iter()
+            .rev()
+            .find(|e| e.id == id)
+            .or_else(|| describe(id))
+    }
+
+    pub fn add(&mut self, entry: SusyEntry) {
+        self.extra.push(entry);
+    }
+
+    /// Adds the entries from the text of a catalog file
+    ///
+    /// Every line has the form `id, family, model`, the id can be decimal or hex with a `0x`
+    /// prefix. Empty lines and lines starting with `#` are ignored.
+    pub fn load_str(&mut self, text: &str) -> Result<(), SusyFileError> {
+        let mut entries = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |reason| SusyFileError {
+                line: i + 1,
+                reason,
+            };
+            let mut fields = line.splitn(3, ',').map(str::trim);
+            let (Some(id), Some(family), Some(model)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(error("expected id, family and model"));
+            };
+            let id = match id.strip_prefix("0x").or_else(|| id.strip_prefix("0X")) {
+                Some(hex) => u32::from_str_radix(hex, 16),
+                None => id.parse(),
+            }
+            .map_err(|_| error("invalid id"))?;
+            entries.push(SusyEntry {
+                id,
+                family: Cow::Owned(family.to_string()),
+                model: Cow::Owned(model.to_string()),
+            });
+        }
+        self.extra.extend(entries);
+        Ok(())
+    }
+
+    /// Adds the entries from a catalog file, see [`SusyCatalog::load_str`] for the format
+    #[cfg(feature = "std")]
+    pub fn load_file<P: AsRef<std::path::Path>>(&mut self, path: P) -> std::io::Result<()> {
+        let text = std::fs::read_to_string(path)?;
+        self.load_str(&text)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// A line of a catalog file could not be parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SusyFileError {
+    /// Line number, starting at 1
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for SusyFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid SUSy-ID entry in line {}: {}",
+            self.line, self.reason
+        )
+    }
+}
+
+impl core::error::Error for SusyFileError {}
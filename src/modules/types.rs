@@ -1,25 +1,41 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(u32)]
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::decoder::CustomModuleContent;
+use super::span::ModuleSpan;
+
+/// Type of a module, the raw id of unknown types is kept in [`ModuleType::Unknown`]
+///
+/// [`ModuleType::from_id`] and `u32::from` convert losslessly between the two, `TryFrom<u32>`
+/// rejects ids that are not known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ModuleType {
-    LevelStartMt = 0x0000,
-    LevelEndMt = 0x0001,
-    PauseMt = 0x0002,
-    LoopStartMt = 0x0003,
-    LoopEndMt = 0x0004,
-    FirmwareverMt = 0x1000,
-    TextMt = 0x1001,
-    LoginMt = 0x2000,
-    FwChkMt = 0x2001,
-    CondChkMt = 0x2002,
-    FirmwareMt = 0x2003,
-    LogoutMt = 0x2004,
-    UpFmt10Mt = 0x3000,
-    UnknownMt = u32::MAX,
-}
-
-impl From<u32> for ModuleType {
-    fn from(v: u32) -> Self {
-        match v {
+    LevelStartMt,
+    LevelEndMt,
+    PauseMt,
+    LoopStartMt,
+    LoopEndMt,
+    FirmwareverMt,
+    TextMt,
+    LoginMt,
+    FwChkMt,
+    CondChkMt,
+    FirmwareMt,
+    LogoutMt,
+    UpFmt10Mt,
+    /// A module type that this crate does not know, with its raw id
+    ///
+    /// This should only hold ids that are not one of the other variants,
+    /// `ModuleType::Unknown(0x0000)` is written the same way as `LevelStartMt`.
+    Unknown(u32),
+}
+
+impl ModuleType {
+    /// Converts a raw id from a module header, unknown ids become [`ModuleType::Unknown`]
+    pub fn from_id(id: u32) -> Self {
+        match id {
             0x0000 => ModuleType::LevelStartMt,
             0x0001 => ModuleType::LevelEndMt,
             0x0002 => ModuleType::PauseMt,
@@ -33,11 +49,64 @@ impl From<u32> for ModuleType {
             0x2003 => ModuleType::FirmwareMt,
             0x2004 => ModuleType::LogoutMt,
             0x3000 => ModuleType::UpFmt10Mt,
-            _ => ModuleType::UnknownMt,
+            _ => ModuleType::Unknown(id),
+        }
+    }
+
+    /// Returns the raw id that is written to the module header
+    pub fn id(self) -> u32 {
+        match self {
+            ModuleType::LevelStartMt => 0x0000,
+            ModuleType::LevelEndMt => 0x0001,
+            ModuleType::PauseMt => 0x0002,
+            ModuleType::LoopStartMt => 0x0003,
+            ModuleType::LoopEndMt => 0x0004,
+            ModuleType::FirmwareverMt => 0x1000,
+            ModuleType::TextMt => 0x1001,
+            ModuleType::LoginMt => 0x2000,
+            ModuleType::FwChkMt => 0x2001,
+            ModuleType::CondChkMt => 0x2002,
+            ModuleType::FirmwareMt => 0x2003,
+            ModuleType::LogoutMt => 0x2004,
+            ModuleType::UpFmt10Mt => 0x3000,
+            ModuleType::Unknown(id) => id,
+        }
+    }
+
+    pub fn is_known(self) -> bool {
+        !matches!(self, ModuleType::Unknown(_))
+    }
+}
+
+impl From<ModuleType> for u32 {
+    fn from(module_type: ModuleType) -> Self {
+        module_type.id()
+    }
+}
+
+impl TryFrom<u32> for ModuleType {
+    type Error = UnknownModuleType;
+
+    fn try_from(id: u32) -> Result<Self, Self::Error> {
+        match ModuleType::from_id(id) {
+            ModuleType::Unknown(id) => Err(UnknownModuleType(id)),
+            module_type => Ok(module_type),
         }
     }
 }
 
+/// Returned by `ModuleType::try_from` for ids that are not known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownModuleType(pub u32);
+
+impl fmt::Display for UnknownModuleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown module type 0x{:04x}", self.0)
+    }
+}
+
+impl core::error::Error for UnknownModuleType {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Up2Header {
     /// Should be 977358163 (0x3a414d53) for a valid header
@@ -52,6 +121,78 @@ pub struct Up2Header {
 pub struct Module {
     pub header: ModuleHeader,
     pub content: ModuleContent,
+    /// Where the module is located in the file
+    pub span: ModuleSpan,
+    /// Problems that were found while parsing, but did not stop the module from being parsed
+    pub warnings: Vec<ModuleWarning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleWarning {
+    /// The Adler-32 checksum in the module header does not match the module body
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// Corrupted data in front of this module was skipped in recovery mode
+    SkippedBytes { start: usize, end: usize },
+}
+
+impl fmt::Display for ModuleWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModuleWarning::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "Adler-32 checksum mismatch (expected 0x{:08x}, got 0x{:08x})",
+                expected, actual
+            ),
+            ModuleWarning::SkippedBytes { start, end } => write!(
+                f,
+                "Skipped {} corrupted bytes (0x{:x}..0x{:x})",
+                end - start,
+                start,
+                end
+            ),
+        }
+    }
+}
+
+/// Controls what happens when the Adler-32 checksum of a module does not match its header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChecksumMode {
+    /// Return an error for the module
+    #[default]
+    Strict,
+    /// Parse the module anyway, but attach a warning to it
+    Lenient,
+    /// Do not verify the checksum at all
+    Off,
+}
+
+/// Checks and limits of the parsers, passed to them as part of a
+/// [`ParserConfig`](super::config::ParserConfig)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub checksum_mode: ChecksumMode,
+    /// Modules with a longer body are rejected before anything is allocated for them
+    pub max_module_size: usize,
+    /// Files are rejected as soon as a module would end after this many bytes
+    pub max_file_size: usize,
+    /// After a broken module, search for the next module with a known type, a valid length and a
+    /// matching Adler-32 checksum instead of giving up. Supported by `Up2Parser` and `Up2Slice`,
+    /// `AsyncUp2Parser` rejects it.
+    ///
+    /// One search checksums at most four times `max_module_size` bytes, larger candidates are
+    /// passed over after that.
+    pub recover: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            checksum_mode: ChecksumMode::default(),
+            max_module_size: 64 * 1024 * 1024,
+            max_file_size: 1024 * 1024 * 1024,
+            recover: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -78,6 +219,130 @@ pub enum ModuleContent {
     Logout(LogoutModule),
     UpFmt10(UpFmt10Module),
     Unknown(UnknownModule),
+    /// Decoded by a registered [`ModuleDecoder`](super::decoder::ModuleDecoder)
+    Custom(CustomModuleContent),
+}
+
+impl ModuleContent {
+    /// Returns the module type that matches this content
+    pub fn module_type(&self) -> ModuleType {
+        match self {
+            ModuleContent::LevelStart(_) => ModuleType::LevelStartMt,
+            ModuleContent::LevelEnd(_) => ModuleType::LevelEndMt,
+            ModuleContent::Pause(_) => ModuleType::PauseMt,
+            ModuleContent::LoopStart(_) => ModuleType::LoopStartMt,
+            ModuleContent::LoopEnd(_) => ModuleType::LoopEndMt,
+            ModuleContent::Firmwarever(_) => ModuleType::FirmwareverMt,
+            ModuleContent::Text(_) => ModuleType::TextMt,
+            ModuleContent::Login(_) => ModuleType::LoginMt,
+            ModuleContent::FwChk(_) => ModuleType::FwChkMt,
+            ModuleContent::CondChk(_) => ModuleType::CondChkMt,
+            ModuleContent::Firmware(_) => ModuleType::FirmwareMt,
+            ModuleContent::Logout(_) => ModuleType::LogoutMt,
+            ModuleContent::UpFmt10(_) => ModuleType::UpFmt10Mt,
+            ModuleContent::Unknown(m) => ModuleType::Unknown(m.module_type),
+            ModuleContent::Custom(m) => ModuleType::from_id(m.module_type),
+        }
+    }
+
+    /// Returns the SMA Data header of command modules
+    pub fn data_header(&self) -> Option<&SmaDataHeader> {
+        match self {
+            ModuleContent::Login(m) => Some(&m.data_header),
+            ModuleContent::FwChk(m) => Some(&m.data_header),
+            ModuleContent::CondChk(m) => Some(&m.data_header),
+            ModuleContent::Firmware(m) => Some(&m.data_header),
+            ModuleContent::Logout(m) => Some(&m.data_header),
+            _ => None,
+        }
+    }
+
+    pub fn data_header_mut(&mut self) -> Option<&mut SmaDataHeader> {
+        match self {
+            ModuleContent::Login(m) => Some(&mut m.data_header),
+            ModuleContent::FwChk(m) => Some(&mut m.data_header),
+            ModuleContent::CondChk(m) => Some(&mut m.data_header),
+            ModuleContent::Firmware(m) => Some(&mut m.data_header),
+            ModuleContent::Logout(m) => Some(&mut m.data_header),
+            _ => None,
+        }
+    }
+}
+
+/// Borrowed version of [`ModuleContent`], variable length data points into the parsed buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleContentRef<'a> {
+    LevelStart(LevelStartModule),
+    LevelEnd(LevelEndModule),
+    Pause(PauseModule),
+    LoopStart(LoopStartModule),
+    LoopEnd(LoopEndModule),
+    Firmwarever(FirmwareverModule),
+    Text(TextModuleRef<'a>),
+    Login(LoginModule),
+    FwChk(FwChkModule),
+    CondChk(CondChkModule),
+    Firmware(FirmwareModuleRef<'a>),
+    Logout(LogoutModule),
+    UpFmt10(UpFmt10ModuleRef<'a>),
+    Unknown(UnknownModuleRef<'a>),
+    Custom(CustomModuleContent),
+}
+
+impl ModuleContentRef<'_> {
+    /// Returns the SMA Data header of command modules
+    pub fn data_header(&self) -> Option<&SmaDataHeader> {
+        match self {
+            ModuleContentRef::Login(m) => Some(&m.data_header),
+            ModuleContentRef::FwChk(m) => Some(&m.data_header),
+            ModuleContentRef::CondChk(m) => Some(&m.data_header),
+            ModuleContentRef::Firmware(m) => Some(&m.data_header),
+            ModuleContentRef::Logout(m) => Some(&m.data_header),
+            _ => None,
+        }
+    }
+
+    pub fn into_owned(self) -> ModuleContent {
+        match self {
+            ModuleContentRef::LevelStart(m) => ModuleContent::LevelStart(m),
+            ModuleContentRef::LevelEnd(m) => ModuleContent::LevelEnd(m),
+            ModuleContentRef::Pause(m) => ModuleContent::Pause(m),
+            ModuleContentRef::LoopStart(m) => ModuleContent::LoopStart(m),
+            ModuleContentRef::LoopEnd(m) => ModuleContent::LoopEnd(m),
+            ModuleContentRef::Firmwarever(m) => ModuleContent::Firmwarever(m),
+            ModuleContentRef::Text(m) => ModuleContent::Text(m.into_owned()),
+            ModuleContentRef::Login(m) => ModuleContent::Login(m),
+            ModuleContentRef::FwChk(m) => ModuleContent::FwChk(m),
+            ModuleContentRef::CondChk(m) => ModuleContent::CondChk(m),
+            ModuleContentRef::Firmware(m) => ModuleContent::Firmware(m.into_owned()),
+            ModuleContentRef::Logout(m) => ModuleContent::Logout(m),
+            ModuleContentRef::UpFmt10(m) => ModuleContent::UpFmt10(m.into_owned()),
+            ModuleContentRef::Unknown(m) => ModuleContent::Unknown(m.into_owned()),
+            ModuleContentRef::Custom(m) => ModuleContent::Custom(m),
+        }
+    }
+}
+
+/// Borrowed version of [`Module`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleRef<'a> {
+    pub header: ModuleHeader,
+    pub content: ModuleContentRef<'a>,
+    /// Where the module is located in the file
+    pub span: ModuleSpan,
+    /// Problems that were found while parsing, but did not stop the module from being parsed
+    pub warnings: Vec<ModuleWarning>,
+}
+
+impl ModuleRef<'_> {
+    pub fn into_owned(self) -> Module {
+        Module {
+            header: self.header,
+            content: self.content.into_owned(),
+            span: self.span,
+            warnings: self.warnings,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -119,8 +384,24 @@ pub struct TextModule {
     pub data: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct LoginModule {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextModuleRef<'a> {
+    pub data: &'a str,
+}
+
+impl TextModuleRef<'_> {
+    pub fn into_owned(self) -> TextModule {
+        TextModule {
+            data: self.data.to_owned(),
+        }
+    }
+}
+
+/// The 28 byte SMA Data header at the start of every command module
+///
+/// Shared by the login, FwChk, CondChk, firmware and logout modules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SmaDataHeader {
     pub ctrl: u16,
     pub dst_susy: u16,
     pub dst_ser: u32,
@@ -129,35 +410,32 @@ pub struct LoginModule {
     pub src_susy: u16,
     pub src_ser: u32,
     pub src_dev: u8,
+    // The CondChk module calls this src_kkt in the internal names from reverse-engineering the
+    // firmware, which is most likely a typo
     pub src_fkt: u8,
     pub cmd: u8,
     pub pcnt: u8,
     pub obj_num: u16,
+    /// Length of the data after `p0`
     pub dat_len: u16,
     pub p0: u32,
+}
+
+/// `Debug` does not show the password, so modules can be logged safely
+#[derive(Clone, PartialEq, Eq)]
+pub struct LoginModule {
+    pub data_header: SmaDataHeader,
     pub p1: u32,
     pub p2: u32,
     pub p3: u32,
-    pub password: String,
+    /// The password as stored in the file, see [`LoginModule::decoded_password`]
+    pub password: [u8; 12],
     pub mode: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FwChkModule {
-    pub ctrl: u16,
-    pub dst_susy: u16,
-    pub dst_ser: u32,
-    pub dst_dev: u8,
-    pub dst_fkt: u8,
-    pub src_susy: u16,
-    pub src_ser: u32,
-    pub src_dev: u8,
-    pub src_fkt: u8,
-    pub cmd: u8,
-    pub pcnt: u8,
-    pub obj_num: u16,
-    pub dat_len: u16,
-    pub p0: u32,
+    pub data_header: SmaDataHeader,
     pub blk_first: u32,
     pub blk_last: u32,
     pub cond_cnt: u16,
@@ -168,23 +446,7 @@ pub struct FwChkModule {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CondChkModule {
-    pub ctrl: u16,
-    pub dst_susy: u16,
-    pub dst_ser: u32,
-    pub dst_dev: u8,
-    pub dst_fkt: u8,
-    pub src_susy: u16,
-    pub src_ser: u32,
-    pub src_dev: u8,
-    // These names are internal names from reverse-engineering the firmware
-    // This could be a typo, but I'm not sure
-    // it maybe should be src_fkt
-    pub src_kkt: u8,
-    pub cmd: u8,
-    pub pcnt: u8,
-    pub obj_num: u16,
-    pub dat_len: u16,
-    pub p0: u32,
+    pub data_header: SmaDataHeader,
     pub obj_nr: u16,
     pub rec_dw_first: u16,
     pub idx_first: u32,
@@ -199,40 +461,31 @@ pub struct CondChkModule {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FirmwareModule {
-    pub ctrl: u16,
-    pub dst_susy: u16,
-    pub dst_ser: u32,
-    pub dst_dev: u8,
-    pub dst_fkt: u8,
-    pub src_susy: u16,
-    pub src_ser: u32,
-    pub src_dev: u8,
-    pub src_fkt: u8,
-    pub cmd: u8,
-    pub pcnt: u8,
-    pub obj_num: u16,
-    pub dat_len: u16,
-    pub p0: u32,
+    pub data_header: SmaDataHeader,
     pub delay: u32,
     pub data: Vec<u8>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirmwareModuleRef<'a> {
+    pub data_header: SmaDataHeader,
+    pub delay: u32,
+    pub data: &'a [u8],
+}
+
+impl FirmwareModuleRef<'_> {
+    pub fn into_owned(self) -> FirmwareModule {
+        FirmwareModule {
+            data_header: self.data_header,
+            delay: self.delay,
+            data: self.data.to_vec(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LogoutModule {
-    pub ctrl: u16,
-    pub dst_susy: u16,
-    pub dst_ser: u32,
-    pub dst_dev: u8,
-    pub dst_fkt: u8,
-    pub src_susy: u16,
-    pub src_ser: u32,
-    pub src_dev: u8,
-    pub src_fkt: u8,
-    pub cmd: u8,
-    pub pcnt: u8,
-    pub obj_num: u16,
-    pub dat_len: u16,
-    pub p0: u32,
+    pub data_header: SmaDataHeader,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -240,6 +493,19 @@ pub struct UpFmt10Module {
     pub data: Vec<u8>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpFmt10ModuleRef<'a> {
+    pub data: &'a [u8],
+}
+
+impl UpFmt10ModuleRef<'_> {
+    pub fn into_owned(self) -> UpFmt10Module {
+        UpFmt10Module {
+            data: self.data.to_vec(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Up2File {
     pub header: Up2Header,
@@ -248,5 +514,23 @@ pub struct Up2File {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct UnknownModule {
+    /// Raw type id from the module header
+    pub module_type: u32,
     pub data: Vec<u8>,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownModuleRef<'a> {
+    /// Raw type id from the module header
+    pub module_type: u32,
+    pub data: &'a [u8],
+}
+
+impl UnknownModuleRef<'_> {
+    pub fn into_owned(self) -> UnknownModule {
+        UnknownModule {
+            module_type: self.module_type,
+            data: self.data.to_vec(),
+        }
+    }
+}
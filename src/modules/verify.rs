@@ -0,0 +1,208 @@
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::digest::{md4, Crc16};
+use super::types::{FirmwareModule, FwChkModule, ModuleContent, Up2File};
+
+/// Size of a firmware block: a 4 byte block number followed by the data
+pub const BLOCK_SIZE: usize = 132;
+
+/// Splits the data of a firmware module into its blocks, returning the block number and data of each
+///
+/// A last block that is shorter than 4 bytes is ignored.
+pub fn firmware_blocks(firmware: &FirmwareModule) -> impl Iterator<Item = (u32, &[u8])> {
+    firmware.data.chunks(BLOCK_SIZE).filter_map(|block| {
+        let (number, data) = block.split_first_chunk::<4>()?;
+        Some((u32::from_le_bytes(*number), data))
+    })
+}
+
+/// Collects the data of the blocks `blk_first..=blk_last` that a FwChk module covers
+///
+/// Returns the data of the blocks in the order they appear in the firmware modules,
+/// without the block numbers, and the number of distinct blocks that were found. A block
+/// that appears more than once is counted once, so it can not make up for a missing one.
+pub fn fw_chk_data<'a>(
+    fw_chk: &FwChkModule,
+    firmware: impl IntoIterator<Item = &'a FirmwareModule>,
+) -> (Vec<u8>, usize) {
+    let mut data = vec![];
+    let mut found = BTreeSet::new();
+    for module in firmware {
+        for (number, block) in firmware_blocks(module) {
+            if (fw_chk.blk_first..=fw_chk.blk_last).contains(&number) {
+                data.extend_from_slice(block);
+                found.insert(number);
+            }
+        }
+    }
+    (data, found.len())
+}
+
+/// The value that is stored in a FwChk module and the value that was computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Check<T> {
+    pub expected: T,
+    pub actual: T,
+}
+
+impl<T: PartialEq> Check<T> {
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+/// Result of verifying one FwChk module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FwChkReport {
+    /// Index of the FwChk module in the file
+    pub module_index: usize,
+    pub blk_first: u32,
+    pub blk_last: u32,
+    /// Number of distinct blocks in the range that were found in the firmware modules
+    pub blocks_found: usize,
+    pub adler32: Check<u32>,
+    /// `None` if no CRC-16 variant was given, the checksum is not checked then
+    pub crc: Option<Check<u16>>,
+    pub md4: Check<[u8; 16]>,
+}
+
+impl FwChkReport {
+    pub fn blocks_expected(&self) -> u64 {
+        (u64::from(self.blk_last) + 1).saturating_sub(u64::from(self.blk_first))
+    }
+
+    /// Checks that all blocks were found and every checked digest matches
+    pub fn passed(&self) -> bool {
+        self.blocks_found as u64 == self.blocks_expected()
+            && self.adler32.passed()
+            && self.crc.is_none_or(|crc| crc.passed())
+            && self.md4.passed()
+    }
+}
+
+/// Recomputes the digests of a FwChk module over the firmware blocks it covers
+///
+/// The CRC is only checked if a variant is given.
+pub fn verify_fw_chk<'a>(
+    module_index: usize,
+    fw_chk: &FwChkModule,
+    firmware: impl IntoIterator<Item = &'a FirmwareModule>,
+    crc: Option<&Crc16>,
+) -> FwChkReport {
+    let (data, blocks_found) = fw_chk_data(fw_chk, firmware);
+    FwChkReport {
+        module_index,
+        blk_first: fw_chk.blk_first,
+        blk_last: fw_chk.blk_last,
+        blocks_found,
+        adler32: Check {
+            expected: fw_chk.adler32,
+            actual: adler32::RollingAdler32::from_buffer(&data).hash(),
+        },
+        crc: crc.map(|crc| Check {
+            expected: fw_chk.crc,
+            actual: crc.checksum(&data),
+        }),
+        md4: Check {
+            expected: fw_chk.md4,
+            actual: md4(&data),
+        },
+    }
+}
+
+/// A CRC-16 variant that gives the checksum of a FwChk module
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrcMatch {
+    pub variant: Crc16,
+    /// The checksum only matches with its two bytes swapped
+    pub byte_swapped: bool,
+}
+
+/// Tries every variant of [`Crc16::CATALOGUE`] on the firmware blocks a FwChk module covers
+pub fn identify_crc16<'a>(
+    fw_chk: &FwChkModule,
+    firmware: impl IntoIterator<Item = &'a FirmwareModule>,
+) -> Vec<CrcMatch> {
+    let (data, _) = fw_chk_data(fw_chk, firmware);
+    let mut matches = vec![];
+    for variant in Crc16::CATALOGUE {
+        let crc = variant.checksum(&data);
+        if crc == fw_chk.crc {
+            matches.push(CrcMatch {
+                variant: *variant,
+                byte_swapped: false,
+            });
+        } else if crc.swap_bytes() == fw_chk.crc {
+            matches.push(CrcMatch {
+                variant: *variant,
+                byte_swapped: true,
+            });
+        }
+    }
+    matches
+}
+
+impl Up2File {
+    /// Returns every FwChk module with the firmware modules it covers
+    ///
+    /// A FwChk module covers the firmware modules before it that are in the same level and are
+    /// sent to the same target. Which of their blocks are checked is selected by
+    /// `blk_first..=blk_last`, see [`fw_chk_data`].
+    pub fn fw_chks(&self) -> Vec<(usize, &FwChkModule, Vec<&FirmwareModule>)> {
+        let mut result = vec![];
+        // Index of the LevelStart module of every open level
+        let mut levels = vec![];
+        let mut firmware = vec![];
+        for (i, module) in self.modules.iter().enumerate() {
+            match &module.content {
+                ModuleContent::LevelStart(_) => levels.push(i),
+                ModuleContent::LevelEnd(_) => {
+                    levels.pop();
+                }
+                ModuleContent::Firmware(m) => firmware.push((levels.last().copied(), m)),
+                ModuleContent::FwChk(m) => {
+                    let level = levels.last().copied();
+                    let target = m.data_header.target();
+                    let covered = firmware
+                        .iter()
+                        .filter(|(l, f)| *l == level && f.data_header.target() == target)
+                        .map(|(_, f)| *f)
+                        .collect();
+                    result.push((i, m, covered));
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Verifies every FwChk module without checking the CRC, see [`Up2File::fw_chks`] for which
+    /// firmware modules are checked
+    ///
+    /// The CRC-16 variant of FwChk modules is not known yet, use [`Up2File::verify_fw_chks_with`]
+    /// once it is.
+    pub fn verify_fw_chks(&self) -> Vec<FwChkReport> {
+        self.fw_chks()
+            .into_iter()
+            .map(|(i, fw_chk, firmware)| verify_fw_chk(i, fw_chk, firmware, None))
+            .collect()
+    }
+
+    /// Verifies every FwChk module, checking the CRC with the given CRC-16 variant
+    pub fn verify_fw_chks_with(&self, crc: &Crc16) -> Vec<FwChkReport> {
+        self.fw_chks()
+            .into_iter()
+            .map(|(i, fw_chk, firmware)| verify_fw_chk(i, fw_chk, firmware, Some(crc)))
+            .collect()
+    }
+
+    /// Finds the CRC-16 variants that match each FwChk module, returning the module index with the matches
+    pub fn identify_crc16(&self) -> Vec<(usize, Vec<CrcMatch>)> {
+        self.fw_chks()
+            .into_iter()
+            .map(|(i, fw_chk, firmware)| (i, identify_crc16(fw_chk, firmware)))
+            .collect()
+    }
+}
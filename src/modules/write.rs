@@ -0,0 +1,189 @@
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::Write;
+
+use super::types::{
+    Module, ModuleContent, ModuleHeader, ModuleWarning, SmaDataHeader, Up2File, Up2Header,
+};
+
+// Takes a header struct and returns the 8 bytes at the start of an up2 file
+pub fn encode_header(header: &Up2Header) -> [u8; 8] {
+    let id = header.header_id.to_le_bytes();
+    [
+        id[0],
+        id[1],
+        id[2],
+        id[3],
+        header.major_version,
+        header.minor_version,
+        header.build_number,
+        header.rev,
+    ]
+}
+
+// Takes a module header struct and returns the 16 bytes in front of the module body
+pub fn encode_module_header(header: &ModuleHeader) -> [u8; 16] {
+    let mut buf = [0; 16];
+    buf[0..4].copy_from_slice(&header.adler.to_le_bytes());
+    buf[4..8].copy_from_slice(&header.module_type.to_le_bytes());
+    buf[8..12].copy_from_slice(&header.susyid.to_le_bytes());
+    buf[12..16].copy_from_slice(&header.len.to_le_bytes());
+    buf
+}
+
+impl SmaDataHeader {
+    /// Encodes the header into the 28 bytes at the start of a command module body
+    pub fn encode(&self) -> [u8; 28] {
+        let mut buf = [0; 28];
+        buf[0..2].copy_from_slice(&self.ctrl.to_le_bytes());
+        buf[2..4].copy_from_slice(&self.dst_susy.to_le_bytes());
+        buf[4..8].copy_from_slice(&self.dst_ser.to_le_bytes());
+        buf[8] = self.dst_dev;
+        buf[9] = self.dst_fkt;
+        buf[10..12].copy_from_slice(&self.src_susy.to_le_bytes());
+        buf[12..16].copy_from_slice(&self.src_ser.to_le_bytes());
+        buf[16] = self.src_dev;
+        buf[17] = self.src_fkt;
+        buf[18] = self.cmd;
+        buf[19] = self.pcnt;
+        buf[20..22].copy_from_slice(&self.obj_num.to_le_bytes());
+        buf[22..24].copy_from_slice(&self.dat_len.to_le_bytes());
+        buf[24..28].copy_from_slice(&self.p0.to_le_bytes());
+        buf
+    }
+}
+
+/// Encodes a module body into the on-disk layout, the inverse of `parse_module_body`
+pub fn encode_module_body(content: &ModuleContent) -> Vec<u8> {
+    let mut buf = vec![];
+    match content {
+        ModuleContent::LevelStart(m) => buf.extend_from_slice(&m.label.to_le_bytes()),
+        ModuleContent::LevelEnd(m) => buf.extend_from_slice(&m.label.to_le_bytes()),
+        ModuleContent::Pause(m) => buf.extend_from_slice(&m.delay.to_le_bytes()),
+        ModuleContent::LoopStart(m) => buf.extend_from_slice(&m.label.to_le_bytes()),
+        ModuleContent::LoopEnd(m) => {
+            buf.extend_from_slice(&m.label.to_le_bytes());
+            buf.extend_from_slice(&m.loops.to_le_bytes());
+        }
+        ModuleContent::Firmwarever(m) => {
+            buf.extend_from_slice(&[m.major_version, m.minor_version, m.build_number, m.rev])
+        }
+        ModuleContent::Text(m) => buf.extend_from_slice(m.data.as_bytes()),
+        ModuleContent::Login(m) => {
+            buf.extend_from_slice(&m.data_header.encode());
+            buf.extend_from_slice(&m.p1.to_le_bytes());
+            buf.extend_from_slice(&m.p2.to_le_bytes());
+            buf.extend_from_slice(&m.p3.to_le_bytes());
+            buf.extend_from_slice(&m.password);
+            buf.extend_from_slice(&m.mode.to_le_bytes());
+        }
+        ModuleContent::FwChk(m) => {
+            buf.extend_from_slice(&m.data_header.encode());
+            buf.extend_from_slice(&m.blk_first.to_le_bytes());
+            buf.extend_from_slice(&m.blk_last.to_le_bytes());
+            buf.extend_from_slice(&m.cond_cnt.to_le_bytes());
+            buf.extend_from_slice(&m.crc.to_le_bytes());
+            buf.extend_from_slice(&m.adler32.to_le_bytes());
+            buf.extend_from_slice(&m.md4);
+        }
+        ModuleContent::CondChk(m) => {
+            buf.extend_from_slice(&m.data_header.encode());
+            buf.extend_from_slice(&m.obj_nr.to_le_bytes());
+            buf.extend_from_slice(&m.rec_dw_first.to_le_bytes());
+            buf.extend_from_slice(&m.idx_first.to_le_bytes());
+            buf.extend_from_slice(&m.bitmask.to_le_bytes());
+            buf.extend_from_slice(&m.lo_bound.to_le_bytes());
+            buf.extend_from_slice(&m.hi_bound.to_le_bytes());
+            buf.extend_from_slice(&[m.no_obj, m.dat_valid, m.res_1, m.res_2]);
+        }
+        ModuleContent::Firmware(m) => {
+            buf.extend_from_slice(&m.data_header.encode());
+            buf.extend_from_slice(&m.delay.to_le_bytes());
+            buf.extend_from_slice(&m.data);
+        }
+        ModuleContent::Logout(m) => buf.extend_from_slice(&m.data_header.encode()),
+        ModuleContent::UpFmt10(m) => buf.extend_from_slice(&m.data),
+        ModuleContent::Unknown(m) => buf.extend_from_slice(&m.data),
+        ModuleContent::Custom(m) => buf.extend_from_slice(&m.value.encode()),
+    }
+    buf
+}
+
+/// Encodes a module body and returns it with a module header that has the matching length and checksum
+///
+/// A module that was parsed with a wrong checksum (see [`ModuleWarning::ChecksumMismatch`]) keeps
+/// that checksum as long as its encoded body is unchanged.
+pub fn encode_module(module: &Module) -> (ModuleHeader, Vec<u8>) {
+    let body = encode_module_body(&module.content);
+    let mut adler = adler32::RollingAdler32::from_buffer(&body).hash();
+    let unchanged = body.len() == module.header.len as usize
+        && module.warnings.iter().any(|warning| {
+            matches!(warning, ModuleWarning::ChecksumMismatch { expected, actual }
+                if *expected == module.header.adler && *actual == adler)
+        });
+    if unchanged {
+        adler = module.header.adler;
+    }
+    let header = ModuleHeader {
+        adler,
+        module_type: module.header.module_type,
+        susyid: module.header.susyid,
+        len: body.len() as u32,
+    };
+    (header, body)
+}
+
+/// Writes up2 files
+///
+/// The length and Adler-32 checksum in each module header are recomputed from the encoded body,
+/// so modules can be changed before writing them. Unchanged modules of a parsed file keep their
+/// checksum, even if it is wrong, so writing a parsed file produces exactly the bytes that were
+/// parsed. Files parsed with [`ChecksumMode::Off`](super::types::ChecksumMode::Off) get correct
+/// checksums, as their wrong checksums are not known.
+#[cfg(feature = "std")]
+pub struct Up2Writer<W: Write> {
+    writer: W,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Up2Writer<W> {
+    /// Creates a writer and writes the file header
+    pub fn new(mut writer: W, header: &Up2Header) -> std::io::Result<Self> {
+        writer.write_all(&encode_header(header))?;
+        Ok(Up2Writer { writer })
+    }
+
+    pub fn write_module(&mut self, module: &Module) -> std::io::Result<()> {
+        let (header, body) = encode_module(module);
+        self.writer.write_all(&encode_module_header(&header))?;
+        self.writer.write_all(&body)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl Up2File {
+    /// Writes the file in the on-disk up2 format
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        let mut writer = Up2Writer::new(writer, &self.header)?;
+        for module in &self.modules {
+            writer.write_module(module)?;
+        }
+        writer.into_inner().flush()
+    }
+
+    /// Encodes the file in the on-disk up2 format, see [`Up2Writer`] for how module headers are filled in
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = encode_header(&self.header).to_vec();
+        for module in &self.modules {
+            let (header, body) = encode_module(module);
+            buf.extend_from_slice(&encode_module_header(&header));
+            buf.extend_from_slice(&body);
+        }
+        buf
+    }
+}
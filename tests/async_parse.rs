@@ -0,0 +1,141 @@
+#![cfg(feature = "async")]
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::StreamExt;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use sma_update_parser::modules::async_parse::AsyncUp2Parser;
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::config::ParserConfig;
+use sma_update_parser::modules::error::Up2Error;
+use sma_update_parser::modules::types::*;
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+fn sample() -> Up2File {
+    let firmware = FirmwareModule {
+        data_header: SmaDataHeader::default(),
+        delay: 100,
+        data: vec![0x55; 264],
+    };
+    Up2Builder::new()
+        .version(1, 2, 3, 4)
+        .level_start(1)
+        .text("Sunny Boy")
+        .firmware(firmware)
+        .level_end(1)
+        .build()
+}
+
+/// Returns `data` and then fails on every read
+struct FailingReader {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl AsyncRead for FailingReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let rest = &self.data[self.pos..];
+        if rest.is_empty() {
+            return Poll::Ready(Err(io::Error::other("device unplugged")));
+        }
+        let len = rest.len().min(buf.remaining());
+        buf.put_slice(&rest[..len]);
+        self.pos += len;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn stream_yields_the_same_modules() {
+    let file = sample();
+    let bytes = file.to_bytes();
+    let modules: Vec<Module> = block_on(async {
+        let parser = AsyncUp2Parser::new(&bytes[..]).await.unwrap();
+        assert_eq!(parser.header, file.header);
+        parser.into_stream().collect::<Vec<_>>().await
+    })
+    .into_iter()
+    .collect::<Result<_, _>>()
+    .unwrap();
+    assert_eq!(modules, file.modules);
+}
+
+#[test]
+fn stream_ends_after_a_read_error() {
+    let bytes = sample().to_bytes();
+    // Fails in the middle of the text module body
+    let reader = FailingReader {
+        data: bytes[..40].to_vec(),
+        pos: 0,
+    };
+    let results = block_on(async {
+        let parser = AsyncUp2Parser::new(reader).await.unwrap();
+        parser.into_stream().take(10).collect::<Vec<_>>().await
+    });
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    match &results[1] {
+        Err(Up2Error::ModuleRead {
+            module_index: 1,
+            module_type: None,
+            offset: 28,
+            source,
+        }) => assert!(matches!(**source, Up2Error::Io(_))),
+        result => panic!("Expected a read error in module 1, got {:?}", result),
+    }
+}
+
+#[test]
+fn truncated_body_is_an_error() {
+    let bytes = sample().to_bytes();
+    let results = block_on(async {
+        let parser = AsyncUp2Parser::new(&bytes[..50]).await.unwrap();
+        parser.into_stream().collect::<Vec<_>>().await
+    });
+    assert_eq!(results.len(), 2);
+    assert!(matches!(
+        &results[1],
+        Err(Up2Error::TruncatedBody {
+            module_index: 1,
+            offset: 28,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn recovery_is_rejected() {
+    let bytes = sample().to_bytes();
+    let config = ParserConfig::from(ParseOptions {
+        recover: true,
+        ..ParseOptions::default()
+    });
+    let result = block_on(async {
+        let parser = AsyncUp2Parser::new(&bytes[..]).await.unwrap();
+        parser.with_config(config).map(|_| ())
+    });
+    assert!(matches!(result, Err(Up2Error::Unsupported("Recovery"))));
+
+    // Other options are accepted
+    let config = ParserConfig::new().with_checksum_mode(ChecksumMode::Off);
+    let modules = block_on(async {
+        let parser = AsyncUp2Parser::new(&bytes[..]).await.unwrap();
+        let parser = parser.with_config(config).unwrap();
+        parser.into_stream().collect::<Vec<_>>().await
+    });
+    assert_eq!(modules.len(), sample().modules.len());
+}
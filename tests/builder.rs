@@ -0,0 +1,79 @@
+use sma_update_parser::modules::builder::{Up2Builder, MAX_FIRMWARE_DATA};
+use sma_update_parser::modules::parse::Up2Parser;
+use sma_update_parser::modules::types::*;
+
+#[test]
+fn built_file_is_accepted_by_the_parser() {
+    let firmware = FirmwareModule {
+        data_header: SmaDataHeader {
+            ctrl: 0xa0,
+            dst_susy: 0x7d,
+            dst_ser: 0xffffffff,
+            dst_dev: 0,
+            dst_fkt: 0,
+            src_susy: 0x78,
+            src_ser: 1234,
+            src_dev: 0,
+            src_fkt: 0,
+            cmd: 0x0e,
+            pcnt: 0,
+            obj_num: 0,
+            dat_len: 0,
+            p0: 0,
+        },
+        delay: 100,
+        data: vec![0; 132],
+    };
+    let file = Up2Builder::new()
+        .version(1, 0, 0, 0)
+        .susyid(0x7d)
+        .level_start(1)
+        .firmware_version(3, 10, 5, b'R')
+        .loop_start(2)
+        .firmware(firmware)
+        .pause(1000)
+        .loop_end(2, 3)
+        .level_end(1)
+        .build();
+
+    let bytes = file.to_bytes();
+    let parser = Up2Parser::new(&bytes[..]).unwrap();
+    assert_eq!(parser.header, file.header);
+    let modules: Vec<Module> = parser.collect::<Result<_, _>>().unwrap();
+    assert_eq!(modules, file.modules);
+    match &modules[3].content {
+        ModuleContent::Firmware(firmware) => assert_eq!(firmware.data_header.dat_len, 136),
+        content => panic!("Expected firmware module, got {:?}", content),
+    }
+}
+
+#[test]
+fn large_firmware_is_split_into_modules() {
+    let data: Vec<u8> = (0..MAX_FIRMWARE_DATA + 1000).map(|i| i as u8).collect();
+    let firmware = FirmwareModule {
+        data_header: SmaDataHeader::default(),
+        delay: 100,
+        data: data.clone(),
+    };
+    let file = Up2Builder::new().firmware(firmware).build();
+    assert_eq!(MAX_FIRMWARE_DATA % 132, 0);
+
+    let parsed = Up2File::from_bytes(&file.to_bytes()).unwrap();
+    let modules: Vec<&FirmwareModule> = parsed
+        .modules
+        .iter()
+        .map(|m| match &m.content {
+            ModuleContent::Firmware(firmware) => firmware,
+            content => panic!("Expected firmware module, got {:?}", content),
+        })
+        .collect();
+    assert_eq!(modules.len(), 2);
+    assert_eq!(modules[0].data.len(), MAX_FIRMWARE_DATA);
+    assert_eq!(
+        usize::from(modules[0].data_header.dat_len),
+        4 + MAX_FIRMWARE_DATA
+    );
+    assert_eq!(modules[1].data_header.dat_len, 4 + 1000);
+    assert!(modules.iter().all(|m| m.delay == 100));
+    assert_eq!([&modules[0].data[..], &modules[1].data[..]].concat(), data);
+}
@@ -0,0 +1,79 @@
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::config::ParserConfig;
+use sma_update_parser::modules::error::Up2Error;
+use sma_update_parser::modules::parse::Up2Parser;
+use sma_update_parser::modules::types::*;
+
+/// Returns a file whose text module (index 1, at offset 28) has a wrong checksum, and its correct checksum
+fn corrupted_file() -> (Vec<u8>, u32) {
+    let file = Up2Builder::new()
+        .level_start(1)
+        .text("Sunny Boy")
+        .level_end(1)
+        .build();
+    let adler = file.modules[1].header.adler;
+    let mut bytes = file.to_bytes();
+    bytes[28..32].copy_from_slice(&(adler ^ 1).to_le_bytes());
+    (bytes, adler)
+}
+
+fn parse(bytes: &[u8], mode: ChecksumMode) -> Vec<Result<Module, Up2Error>> {
+    Up2Parser::new(bytes)
+        .unwrap()
+        .with_config(ParserConfig::new().with_checksum_mode(mode))
+        .collect()
+}
+
+#[test]
+fn strict_mode_rejects_a_checksum_mismatch() {
+    let (bytes, adler) = corrupted_file();
+    let results = parse(&bytes, ChecksumMode::Strict);
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    match &results[1] {
+        Err(Up2Error::ChecksumMismatch {
+            module_index,
+            module_type,
+            offset,
+            expected,
+            actual,
+        }) => {
+            assert_eq!(*module_index, 1);
+            assert_eq!(*module_type, ModuleType::TextMt);
+            assert_eq!(*offset, 28);
+            assert_eq!(*expected, adler ^ 1);
+            assert_eq!(*actual, adler);
+        }
+        result => panic!("Expected a checksum mismatch, got {:?}", result),
+    }
+    assert!(results[2].is_ok());
+}
+
+#[test]
+fn lenient_mode_attaches_a_warning() {
+    let (bytes, adler) = corrupted_file();
+    let modules: Vec<Module> = parse(&bytes, ChecksumMode::Lenient)
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        modules[1].warnings,
+        vec![ModuleWarning::ChecksumMismatch {
+            expected: adler ^ 1,
+            actual: adler,
+        }]
+    );
+    assert!(modules[0].warnings.is_empty());
+    assert!(modules[2].warnings.is_empty());
+}
+
+#[test]
+fn off_mode_skips_the_check() {
+    let (bytes, _) = corrupted_file();
+    let modules: Vec<Module> = parse(&bytes, ChecksumMode::Off)
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert!(modules.iter().all(|m| m.warnings.is_empty()));
+    assert!(matches!(&modules[1].content, ModuleContent::Text(t) if t.data == "Sunny Boy"));
+}
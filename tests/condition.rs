@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+
+use sma_update_parser::modules::condition::*;
+use sma_update_parser::modules::types::*;
+
+fn cond_chk(no_obj: u8, dat_valid: u8) -> CondChkModule {
+    CondChkModule {
+        data_header: SmaDataHeader::default(),
+        obj_nr: 0x2148,
+        rec_dw_first: 0,
+        idx_first: 1,
+        bitmask: 0x00ffff00,
+        lo_bound: 0x00030000,
+        hi_bound: 0x0003ff00,
+        no_obj,
+        dat_valid,
+        res_1: 0,
+        res_2: 0,
+    }
+}
+
+#[test]
+fn condition_is_evaluated_against_object_values() {
+    let check = cond_chk(0, 1);
+    let mut objects = BTreeMap::new();
+    assert_eq!(
+        check.evaluate(&objects),
+        CondResult::Skip(CondReason::MissingObject)
+    );
+    assert!(cond_chk(1, 1).evaluate(&objects).proceeds());
+
+    objects.insert(check.object_key(), ObjectValue::Invalid);
+    assert_eq!(
+        check.evaluate(&objects),
+        CondResult::Skip(CondReason::InvalidData)
+    );
+    assert!(cond_chk(0, 0).evaluate(&objects).proceeds());
+
+    objects.insert(check.object_key(), ObjectValue::Value(0xff031234));
+    assert_eq!(
+        check.evaluate(&objects),
+        CondResult::Proceed(CondReason::InRange {
+            value: 0xff031234,
+            masked: 0x00031200
+        })
+    );
+
+    let result = check.evaluate(&|_| Some(ObjectValue::Value(0x00040000)));
+    assert!(!result.proceeds());
+    assert_eq!(
+        result.to_string(),
+        "skip: value 0x00040000 (masked 0x00040000) is outside of the bounds"
+    );
+}
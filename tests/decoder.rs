@@ -0,0 +1,98 @@
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::config::ParserConfig;
+use sma_update_parser::modules::decoder::{CustomModule, DecoderRegistry};
+use sma_update_parser::modules::parse::Up2Parser;
+use sma_update_parser::modules::slice::Up2Slice;
+use sma_update_parser::modules::types::*;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Counter {
+    value: u16,
+}
+
+impl CustomModule for Counter {
+    fn encode(&self) -> Vec<u8> {
+        self.value.to_le_bytes().to_vec()
+    }
+}
+
+fn decode_counter(body: &[u8]) -> Result<Box<dyn CustomModule>, String> {
+    let bytes: [u8; 2] = body
+        .try_into()
+        .map_err(|_| "expected 2 bytes".to_string())?;
+    Ok(Box::new(Counter {
+        value: u16::from_le_bytes(bytes),
+    }))
+}
+
+#[test]
+fn registered_decoder_is_used_for_its_type() {
+    let bytes = Up2Builder::new()
+        .level_start(1)
+        .unknown(0x4242, vec![7, 0])
+        .unknown(0x4243, vec![7, 0])
+        .build()
+        .to_bytes();
+
+    let modules: Vec<_> = Up2Parser::new(bytes.as_slice())
+        .unwrap()
+        .with_config(ParserConfig::new().with_decoder(0x4242, decode_counter))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let ModuleContent::Custom(custom) = &modules[1].content else {
+        panic!("expected a custom module, got {:?}", modules[1].content);
+    };
+    assert_eq!(
+        custom.downcast_ref::<Counter>(),
+        Some(&Counter { value: 7 })
+    );
+    assert_eq!(modules[1].module_type(), ModuleType::Unknown(0x4242));
+    assert!(matches!(modules[2].content, ModuleContent::Unknown(_)));
+
+    // Custom modules are written with their own encoding
+    let file = Up2File {
+        header: Up2Parser::new(bytes.as_slice()).unwrap().header,
+        modules,
+    };
+    assert_eq!(file.to_bytes(), bytes);
+}
+
+#[test]
+fn decoder_errors_are_reported() {
+    let bytes = Up2Builder::new()
+        .unknown(0x4242, vec![1, 2, 3])
+        .build()
+        .to_bytes();
+    let mut slice = Up2Slice::new(&bytes)
+        .unwrap()
+        .with_config(ParserConfig::new().with_decoder(0x4242, decode_counter));
+    let err = slice.next().unwrap().unwrap_err();
+    assert!(err.to_string().contains("expected 2 bytes"), "{}", err);
+}
+
+#[test]
+fn registry_and_custom_modules_can_be_cloned() {
+    let bytes = Up2Builder::new()
+        .unknown(0x4242, vec![7, 0])
+        .build()
+        .to_bytes();
+    let mut decoders = DecoderRegistry::new();
+    decoders.register(0x4242, decode_counter);
+
+    for decoders in [decoders.clone(), decoders] {
+        let modules: Vec<Module> = Up2Parser::new(bytes.as_slice())
+            .unwrap()
+            .with_config(ParserConfig::new().with_decoders(decoders))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let module = modules[0].clone();
+        assert_eq!(module, modules[0]);
+        let ModuleContent::Custom(custom) = &module.content else {
+            panic!("expected a custom module, got {:?}", module.content);
+        };
+        assert_eq!(
+            custom.downcast_ref::<Counter>(),
+            Some(&Counter { value: 7 })
+        );
+    }
+}
@@ -0,0 +1,244 @@
+#![cfg(feature = "std")]
+
+use std::io::{Cursor, Read};
+
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::config::ParserConfig;
+use sma_update_parser::modules::error::Up2Error;
+use sma_update_parser::modules::index::Up2Index;
+use sma_update_parser::modules::parse::Up2Parser;
+use sma_update_parser::modules::read::IoReader;
+use sma_update_parser::modules::slice::Up2Slice;
+use sma_update_parser::modules::types::*;
+
+/// Returns `data` and then fails on every read
+struct FailingReader {
+    data: Cursor<Vec<u8>>,
+}
+
+impl Read for FailingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.data.read(buf)? {
+            0 => Err(std::io::Error::other("device unplugged")),
+            n => Ok(n),
+        }
+    }
+}
+
+fn sample() -> Vec<u8> {
+    Up2Builder::new()
+        .level_start(1)
+        .text("Sunny Boy")
+        .level_end(1)
+        .build()
+        .to_bytes()
+}
+
+fn parse_failing(data: &[u8]) -> Vec<Result<(), Up2Error>> {
+    let reader = FailingReader {
+        data: Cursor::new(data.to_vec()),
+    };
+    Up2Parser::new(IoReader::new(reader))
+        .unwrap()
+        .map(|module| module.map(|_| ()))
+        .take(10)
+        .collect()
+}
+
+#[test]
+fn parser_stops_after_a_read_error() {
+    let bytes = sample();
+
+    // Fails right after the file header
+    let results = parse_failing(&bytes[..8]);
+    assert_eq!(results.len(), 1);
+    match &results[0] {
+        Err(Up2Error::ModuleRead {
+            module_index: 0,
+            module_type: None,
+            offset: 8,
+            source,
+        }) => assert!(matches!(**source, Up2Error::Io(_))),
+        result => panic!("Expected a read error in module 0, got {:?}", result),
+    }
+
+    // Fails in the middle of the text module body
+    let results = parse_failing(&bytes[..50]);
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    let error = results[1].as_ref().unwrap_err();
+    assert!(error.is_read_error());
+    assert!(matches!(
+        error,
+        Up2Error::ModuleRead {
+            module_index: 1,
+            module_type: Some(ModuleType::TextMt),
+            offset: 28,
+            ..
+        }
+    ));
+    assert_eq!(
+        error.to_string(),
+        "Reading module 1 (TextMt) at offset 0x1c failed: I/O error: device unplugged"
+    );
+}
+
+#[test]
+fn read_error_while_recovering_has_a_position() {
+    let mut bytes = sample();
+    // Break the checksum of the LevelEnd module at offset 53, the last one in the file
+    bytes[53] ^= 0xff;
+    let reader = FailingReader {
+        data: Cursor::new(bytes),
+    };
+    let options = ParseOptions {
+        recover: true,
+        ..ParseOptions::default()
+    };
+    let results: Vec<_> = Up2Parser::new(IoReader::new(reader))
+        .unwrap()
+        .with_config(ParserConfig::from(options))
+        .take(10)
+        .collect();
+    assert_eq!(results.len(), 4);
+    assert!(matches!(
+        results[2],
+        Err(Up2Error::ChecksumMismatch {
+            module_index: 2,
+            ..
+        })
+    ));
+    // The search for the next module runs into the failing reader
+    match &results[3] {
+        Err(Up2Error::ModuleRead {
+            module_index: 3,
+            module_type: None,
+            offset,
+            source,
+        }) => {
+            assert!(*offset > 53);
+            assert!(matches!(**source, Up2Error::Io(_)));
+        }
+        result => panic!("Expected a read error after module 2, got {:?}", result),
+    }
+}
+
+/// A file with a module of the given type and body, with a valid checksum
+fn single_module(module_type: ModuleType, data: Vec<u8>) -> Vec<u8> {
+    let module_type = module_type.id();
+    Up2Builder::new()
+        .raw_module(
+            module_type,
+            ModuleContent::Unknown(UnknownModule { module_type, data }),
+        )
+        .build()
+        .to_bytes()
+}
+
+/// Runs all parsers over `bytes` and returns the first error of each
+fn first_errors(bytes: &[u8]) -> [Up2Error; 3] {
+    let streaming = Up2Parser::new(bytes)
+        .and_then(|parser| parser.collect::<Result<Vec<_>, _>>())
+        .unwrap_err();
+    let slice = Up2Slice::new(bytes)
+        .and_then(|parser| parser.collect::<Result<Vec<_>, _>>())
+        .unwrap_err();
+    let index = Up2Index::new(Cursor::new(bytes.to_vec()))
+        .and_then(|mut index| {
+            (0..index.entries().len())
+                .map(|i| index.load(i).unwrap())
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .unwrap_err();
+    [streaming, slice, index]
+}
+
+#[test]
+fn truncated_header_is_an_error() {
+    let bytes = single_module(ModuleType::LevelStartMt, vec![0; 4]);
+    for error in first_errors(&bytes[..5]) {
+        assert!(
+            matches!(
+                error,
+                Up2Error::TruncatedHeader {
+                    module_index: None,
+                    offset: 0
+                }
+            ),
+            "{:?}",
+            error
+        );
+    }
+    for error in first_errors(&bytes[..18]) {
+        assert!(
+            matches!(
+                error,
+                Up2Error::TruncatedHeader {
+                    module_index: Some(0),
+                    offset: 8
+                }
+            ),
+            "{:?}",
+            error
+        );
+    }
+}
+
+#[test]
+fn truncated_body_is_an_error() {
+    let bytes = single_module(ModuleType::TextMt, b"Sunny Boy".to_vec());
+    for error in first_errors(&bytes[..bytes.len() - 3]) {
+        assert!(
+            matches!(
+                error,
+                Up2Error::TruncatedBody {
+                    module_index: 0,
+                    module_type: ModuleType::TextMt,
+                    offset: 8,
+                    expected: 9,
+                    actual: 6
+                }
+            ),
+            "{:?}",
+            error
+        );
+    }
+}
+
+#[test]
+fn short_firmware_module_is_an_error() {
+    let bytes = single_module(ModuleType::FirmwareMt, vec![0; 31]);
+    for error in first_errors(&bytes) {
+        assert!(
+            matches!(
+                error,
+                Up2Error::BadModuleLength {
+                    module_type: ModuleType::FirmwareMt,
+                    len: 31,
+                    ..
+                }
+            ),
+            "{:?}",
+            error
+        );
+    }
+}
+
+#[test]
+fn invalid_utf8_is_an_error() {
+    let bytes = single_module(ModuleType::TextMt, vec![b'S', 0xff, 0xfe]);
+    for error in first_errors(&bytes) {
+        assert!(
+            matches!(
+                error,
+                Up2Error::InvalidUtf8 {
+                    module_index: 0,
+                    module_type: ModuleType::TextMt,
+                    offset: 8
+                }
+            ),
+            "{:?}",
+            error
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::error::Up2Error;
+use sma_update_parser::modules::types::*;
+
+fn sample() -> Up2File {
+    Up2Builder::new()
+        .level_start(1)
+        .text("Sunny Boy")
+        .firmware_version(2, 5, 1, b'R')
+        .text("Sunny Tripower")
+        .firmware_version(3, 0, 0, b'B')
+        .level_end(1)
+        .build()
+}
+
+#[test]
+fn modules_of_type_keeps_file_order() {
+    let file = sample();
+    let texts: Vec<&Module> = file.modules_of_type(ModuleType::TextMt).collect();
+    assert_eq!(texts.len(), 2);
+    assert_eq!(texts[0], &file.modules[1]);
+    assert_eq!(texts[1], &file.modules[3]);
+    assert_eq!(file.modules_of_type(ModuleType::LoginMt).count(), 0);
+}
+
+#[test]
+fn firmware_version_is_the_first_version_module() {
+    let version = sample().firmware_version().cloned().unwrap();
+    assert_eq!(
+        version,
+        FirmwareverModule {
+            major_version: 2,
+            minor_version: 5,
+            build_number: 1,
+            rev: b'R',
+        }
+    );
+    assert_eq!(Up2Builder::new().text("a").build().firmware_version(), None);
+}
+
+#[test]
+fn texts_are_returned_in_order() {
+    let file = sample();
+    assert_eq!(
+        file.texts().collect::<Vec<_>>(),
+        ["Sunny Boy", "Sunny Tripower"]
+    );
+    assert_eq!(Up2Builder::new().build().texts().count(), 0);
+}
+
+#[test]
+fn targets_are_empty_without_command_modules() {
+    assert!(sample().targets().is_empty());
+}
+
+#[test]
+fn parse_and_from_bytes_agree_on_errors() {
+    let file = sample();
+    let text = file.modules[3].span;
+    let mut bytes = file.to_bytes();
+    bytes[text.body_offset] ^= 0xff;
+
+    let streamed = Up2File::parse(&bytes[..]).unwrap_err();
+    let sliced = Up2File::from_bytes(&bytes).unwrap_err();
+    assert_eq!(format!("{:?}", streamed), format!("{:?}", sliced));
+    assert!(matches!(
+        sliced,
+        Up2Error::ChecksumMismatch {
+            module_index: 3,
+            ..
+        }
+    ));
+
+    let truncated = &bytes[..bytes.len() - 2];
+    let streamed = Up2File::parse(truncated).unwrap_err();
+    let sliced = Up2File::from_bytes(truncated).unwrap_err();
+    assert_eq!(format!("{:?}", streamed), format!("{:?}", sliced));
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "std")]
+
+use std::cell::Cell;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::rc::Rc;
+
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::index::Up2Index;
+use sma_update_parser::modules::types::*;
+
+/// Counts the bytes that are actually read
+struct CountingReader {
+    inner: Cursor<Vec<u8>>,
+    read: Rc<Cell<usize>>,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.set(self.read.get() + n);
+        Ok(n)
+    }
+}
+
+impl Seek for CountingReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+fn index_reads_headers_and_loads_modules_on_demand() {
+    let firmware = FirmwareModule {
+        data_header: SmaDataHeader::default(),
+        delay: 100,
+        data: vec![0x55; 4096],
+    };
+    let file = Up2Builder::new()
+        .level_start(1)
+        .firmware(firmware)
+        .text("Sunny Boy")
+        .level_end(1)
+        .build();
+    let bytes = file.to_bytes();
+    let read = Rc::new(Cell::new(0));
+    let reader = CountingReader {
+        inner: Cursor::new(bytes),
+        read: read.clone(),
+    };
+
+    let mut index = Up2Index::new(reader).unwrap();
+    assert_eq!(index.header, file.header);
+    // The file header and one module header per module
+    assert_eq!(read.get(), 8 + 4 * 16);
+    let entries = index.entries().to_vec();
+    assert_eq!(entries.len(), file.modules.len());
+    for (entry, module) in entries.iter().zip(&file.modules) {
+        assert_eq!(entry.header, module.header);
+        assert_eq!(entry.span, module.span);
+        assert_eq!(entry.module_type(), module.content.module_type());
+    }
+
+    // Loading works in any order
+    assert_eq!(index.load(2).unwrap().unwrap(), file.modules[2]);
+    assert_eq!(index.load(0).unwrap().unwrap(), file.modules[0]);
+    assert_eq!(index.load(1).unwrap().unwrap(), file.modules[1]);
+    assert!(index.load(4).is_none());
+}
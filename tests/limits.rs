@@ -0,0 +1,140 @@
+#![cfg(feature = "std")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Cursor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::config::ParserConfig;
+use sma_update_parser::modules::error::Up2Error;
+use sma_update_parser::modules::index::Up2Index;
+use sma_update_parser::modules::parse::Up2Parser;
+use sma_update_parser::modules::read::IoReader;
+use sma_update_parser::modules::slice::Up2Slice;
+use sma_update_parser::modules::types::*;
+
+/// Remembers the largest single allocation
+struct TrackingAllocator;
+
+static LARGEST_ALLOCATION: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LARGEST_ALLOCATION.fetch_max(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        LARGEST_ALLOCATION.fetch_max(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+/// A file with a single text module header that claims a body of almost 4 GiB, followed by 100 bytes
+fn huge_module() -> Vec<u8> {
+    let mut bytes = Up2Builder::new().build().to_bytes();
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&ModuleType::TextMt.id().to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&0xfffffff0u32.to_le_bytes());
+    bytes.extend_from_slice(&[b'a'; 100]);
+    bytes
+}
+
+#[test]
+fn huge_module_is_rejected_without_allocating() {
+    let bytes = huge_module();
+
+    let mut parser = Up2Parser::new(IoReader::new(Cursor::new(bytes.clone()))).unwrap();
+    assert!(matches!(
+        parser.next(),
+        Some(Err(Up2Error::ModuleTooLarge {
+            module_index: 0,
+            offset: 8,
+            len: 0xfffffff0,
+            ..
+        }))
+    ));
+    assert!(parser.next().is_none());
+
+    let mut slice = Up2Slice::new(&bytes).unwrap();
+    assert!(matches!(
+        slice.next(),
+        Some(Err(Up2Error::ModuleTooLarge { .. }))
+    ));
+    assert!(slice.next().is_none());
+
+    assert!(matches!(
+        Up2Index::new(Cursor::new(bytes.clone())),
+        Err(Up2Error::ModuleTooLarge { .. })
+    ));
+
+    // Without limits the body is only read as far as there is data
+    let options = ParseOptions {
+        max_module_size: usize::MAX,
+        max_file_size: usize::MAX,
+        ..ParseOptions::default()
+    };
+    LARGEST_ALLOCATION.store(0, Ordering::Relaxed);
+    let mut parser = Up2Parser::new(IoReader::new(Cursor::new(bytes.clone())))
+        .unwrap()
+        .with_config(ParserConfig::from(options));
+    assert!(matches!(
+        parser.next(),
+        Some(Err(Up2Error::TruncatedBody {
+            expected: 0xfffffff0,
+            actual: 100,
+            ..
+        }))
+    ));
+    assert!(LARGEST_ALLOCATION.load(Ordering::Relaxed) < 1024 * 1024);
+}
+
+#[test]
+fn file_size_limit_is_enforced() {
+    let bytes = Up2Builder::new()
+        .text("Sunny Boy")
+        .text(&"a".repeat(100))
+        .build()
+        .to_bytes();
+    let options = ParseOptions {
+        max_file_size: 100,
+        ..ParseOptions::default()
+    };
+    let is_file_too_large = |error: &Up2Error| {
+        matches!(
+            error,
+            Up2Error::FileTooLarge {
+                module_index: 1,
+                offset: 33,
+                max: 100,
+                ..
+            }
+        )
+    };
+
+    let results: Vec<_> = Up2Parser::new(IoReader::new(Cursor::new(bytes.clone())))
+        .unwrap()
+        .with_config(ParserConfig::from(options))
+        .collect();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    assert!(matches!(&results[1], Err(e) if is_file_too_large(e)));
+
+    let results: Vec<_> = Up2Slice::new(&bytes)
+        .unwrap()
+        .with_config(ParserConfig::from(options))
+        .collect();
+    assert_eq!(results.len(), 2);
+    assert!(matches!(&results[1], Err(e) if is_file_too_large(e)));
+
+    let error = Up2Index::new_with_config(Cursor::new(bytes), options.into()).err();
+    assert!(matches!(&error, Some(e) if is_file_too_large(e)));
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::login::{Timestamp, UserGroup};
+use sma_update_parser::modules::types::*;
+
+fn login(p0: u32, mode: u32) -> LoginModule {
+    LoginModule {
+        data_header: SmaDataHeader {
+            cmd: 0x0c,
+            obj_num: 0xfffd,
+            p0,
+            ..Default::default()
+        },
+        p1: 900,
+        p2: 1700000000,
+        p3: 0,
+        password: LoginModule::encode_password(UserGroup::from_code(p0), "1111"),
+        mode,
+    }
+}
+
+#[test]
+fn login_params_are_typed() {
+    let file = Up2Builder::new().login(login(0x0a, 0x07)).build();
+    let parsed = Up2File::from_bytes(&file.to_bytes()).unwrap();
+    let ModuleContent::Login(login) = &parsed.modules[0].content else {
+        panic!("Expected login module, got {:?}", parsed.modules[0].content);
+    };
+
+    // The user group comes from p0, not from mode
+    assert_eq!(login.user_group(), UserGroup::Installer);
+    assert_eq!(login.decoded_password().as_deref(), Some("1111"));
+    let params = login.params();
+    assert_eq!(params.user_group, UserGroup::Installer);
+    assert_eq!(params.timeout, Duration::from_secs(900));
+    assert_eq!(params.timestamp, Timestamp(1700000000));
+    assert_eq!(params.mode, 0x07);
+    assert_eq!(
+        params.to_string(),
+        "user group: installer, timeout: 900 s, time: 2023-11-14 22:13:20 UTC, \
+         p3: 0x00000000, mode: 0x00000007"
+    );
+}
+
+#[test]
+fn unknown_user_groups_keep_their_code() {
+    let login = login(0x0b, 0x0b);
+    assert_eq!(login.user_group(), UserGroup::Other(0x0b));
+    assert_eq!(login.user_group().to_string(), "0x0b");
+    assert_eq!(login.decoded_password(), None);
+    assert_eq!(UserGroup::from_code(0x07), UserGroup::User);
+    assert_eq!(UserGroup::User.code(), 0x07);
+    assert_eq!(Timestamp(0).to_string(), "1970-01-01 00:00:00 UTC");
+}
+
+#[test]
+fn login_password_is_decoded_and_redacted() {
+    let file = Up2Builder::new().login(login(0x07, 0x07)).build();
+    let parsed = Up2File::from_bytes(&file.to_bytes()).unwrap();
+    let ModuleContent::Login(login) = &parsed.modules[0].content else {
+        panic!("Expected login module, got {:?}", parsed.modules[0].content);
+    };
+    assert_eq!(login.user_group(), UserGroup::User);
+    assert_eq!(login.decoded_password().as_deref(), Some("1111"));
+    assert!(!format!("{:?}", login).contains("1111"));
+    assert!(format!("{:?}", login).contains("<redacted>"));
+}
+
+#[test]
+fn password_encoding_is_selected_by_p0_not_mode() {
+    let mut login = login(0x0a, 0x07);
+    assert_eq!(login.decoded_password().as_deref(), Some("1111"));
+    login.password = LoginModule::encode_password(UserGroup::User, "1111");
+    // Decoded with the installer key the user encoding gives other characters
+    assert_ne!(login.decoded_password().as_deref(), Some("1111"));
+}
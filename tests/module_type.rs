@@ -0,0 +1,41 @@
+use sma_update_parser::modules::types::{ModuleType, UnknownModuleType};
+
+const KNOWN: [(ModuleType, u32); 13] = [
+    (ModuleType::LevelStartMt, 0x0000),
+    (ModuleType::LevelEndMt, 0x0001),
+    (ModuleType::PauseMt, 0x0002),
+    (ModuleType::LoopStartMt, 0x0003),
+    (ModuleType::LoopEndMt, 0x0004),
+    (ModuleType::FirmwareverMt, 0x1000),
+    (ModuleType::TextMt, 0x1001),
+    (ModuleType::LoginMt, 0x2000),
+    (ModuleType::FwChkMt, 0x2001),
+    (ModuleType::CondChkMt, 0x2002),
+    (ModuleType::FirmwareMt, 0x2003),
+    (ModuleType::LogoutMt, 0x2004),
+    (ModuleType::UpFmt10Mt, 0x3000),
+];
+
+#[test]
+fn known_ids_round_trip() {
+    for (module_type, id) in KNOWN {
+        assert_eq!(module_type.id(), id);
+        assert_eq!(u32::from(module_type), id);
+        assert_eq!(ModuleType::from_id(id), module_type);
+        assert_eq!(ModuleType::try_from(id), Ok(module_type));
+        assert!(module_type.is_known());
+    }
+}
+
+#[test]
+fn unknown_ids_round_trip() {
+    let module_type = ModuleType::from_id(0x4242);
+    assert_eq!(module_type, ModuleType::Unknown(0x4242));
+    assert!(!module_type.is_known());
+    assert_eq!(module_type.id(), 0x4242);
+    assert_eq!(u32::from(module_type), 0x4242);
+
+    let error = ModuleType::try_from(0x4242).unwrap_err();
+    assert_eq!(error, UnknownModuleType(0x4242));
+    assert_eq!(error.to_string(), "Unknown module type 0x4242");
+}
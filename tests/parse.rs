@@ -0,0 +1,90 @@
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::error::Up2Error;
+use sma_update_parser::modules::parse::Up2Parser;
+use sma_update_parser::modules::read::Up2Read;
+use sma_update_parser::modules::types::*;
+
+fn assert_send<T: Send>() {}
+
+/// Only compiles if the parser is `Send` for every reader that is
+fn assert_parser_send<R: Up2Read + Send>() {
+    assert_send::<Up2Parser<R>>();
+}
+
+#[test]
+fn parser_is_send_if_the_reader_is() {
+    assert_parser_send::<&[u8]>();
+    assert_parser_send::<Box<dyn Up2Read + Send>>();
+}
+
+fn sample() -> Up2File {
+    Up2Builder::new()
+        .level_start(1)
+        // Contains the header of a 40 byte text module that would end after the next module
+        .text("Xabcd\x01\x10\0\0\0\0\0\0(\0\0\0tail")
+        .text("Sunny Tripower")
+        .level_end(1)
+        .build()
+}
+
+#[test]
+fn into_inner_returns_the_reader_after_the_last_module() {
+    let file = sample();
+    let bytes = file.to_bytes();
+    let text = file.modules[1].span;
+    let mut parser = Up2Parser::new(&bytes[..]).unwrap();
+    parser.next().unwrap().unwrap();
+    parser.next().unwrap().unwrap();
+    assert_eq!(parser.offset(), text.body_offset + text.len);
+    // Nothing is read ahead while parsing valid modules
+    let rest = parser.into_inner();
+    assert_eq!(rest, &bytes[text.body_offset + text.len..]);
+}
+
+#[test]
+fn into_parts_returns_the_bytes_read_ahead() {
+    let file = sample();
+    let first_text = file.modules[1].span;
+    let second_text = file.modules[2].span;
+    let mut bytes = file.to_bytes();
+    bytes[first_text.body_offset] ^= 0xff;
+    let options = ParseOptions {
+        recover: true,
+        ..ParseOptions::default()
+    };
+
+    // The broken module stays in the lookahead, the next module might start inside of it
+    let mut parser = Up2Parser::new(&bytes[..])
+        .unwrap()
+        .with_config(options.into());
+    parser.next().unwrap().unwrap();
+    assert!(matches!(
+        parser.next(),
+        Some(Err(Up2Error::ChecksumMismatch { .. }))
+    ));
+    let offset = parser.offset();
+    let (rest, lookahead) = parser.into_parts();
+    assert_eq!(offset, first_text.header_offset);
+    assert_eq!(
+        lookahead,
+        &bytes[offset..first_text.body_offset + first_text.len]
+    );
+    assert_eq!([&lookahead[..], rest].concat(), &bytes[offset..]);
+
+    // After resynchronizing, the lookahead and the reader still continue at the offset
+    let mut parser = Up2Parser::new(&bytes[..])
+        .unwrap()
+        .with_config(options.into());
+    parser.next().unwrap().unwrap();
+    parser.next().unwrap().unwrap_err();
+    let module = parser.next().unwrap().unwrap();
+    assert_eq!(module.span, second_text);
+    let skipped = first_text.header_offset..second_text.header_offset;
+    assert_eq!(parser.skipped(), &[skipped][..]);
+    let offset = parser.offset();
+    let (rest, lookahead) = parser.into_parts();
+    assert_eq!(offset, second_text.body_offset + second_text.len);
+    // Checking the lookalike header read past the second text module
+    assert!(!lookahead.is_empty());
+    assert_eq!([&lookahead[..], rest].concat(), &bytes[offset..]);
+}
@@ -0,0 +1,101 @@
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::error::{Result, Up2Error};
+use sma_update_parser::modules::parse::Up2Parser;
+use sma_update_parser::modules::read::Up2Read;
+use sma_update_parser::modules::types::*;
+
+/// A source that only implements `Up2Read`, like a serial port on a `no_std` target
+struct Uart {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl Up2Read for Uart {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // One byte at a time
+        let Some(&byte) = self.data.get(self.pos) else {
+            return Ok(0);
+        };
+        match buf.first_mut() {
+            Some(first) => *first = byte,
+            None => return Ok(0),
+        }
+        self.pos += 1;
+        Ok(1)
+    }
+}
+
+fn sample() -> Up2File {
+    Up2Builder::new()
+        .level_start(1)
+        .text("Sunny Boy")
+        .level_end(1)
+        .build()
+}
+
+fn contents(results: impl Iterator<Item = Result<Module>>) -> Vec<ModuleContent> {
+    results.map(|module| module.unwrap().content).collect()
+}
+
+// These have to compile no matter which features are enabled
+#[test]
+fn custom_readers_work_by_reference_and_boxed() {
+    let file = sample();
+    let expected: Vec<_> = file.modules.iter().map(|m| m.content.clone()).collect();
+    let mut uart = Uart {
+        data: file.to_bytes(),
+        pos: 0,
+    };
+    assert_eq!(contents(Up2Parser::new(&mut uart).unwrap()), expected);
+
+    uart.pos = 0;
+    let boxed: Box<dyn Up2Read> = Box::new(uart);
+    assert_eq!(contents(Up2Parser::new(boxed).unwrap()), expected);
+
+    let bytes = file.to_bytes();
+    assert_eq!(contents(Up2Parser::new(&bytes[..]).unwrap()), expected);
+}
+
+#[test]
+fn custom_reader_errors_are_passed_on() {
+    struct Broken;
+    impl Up2Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> Result<usize> {
+            Err(Up2Error::Read("framing error"))
+        }
+    }
+    assert!(matches!(
+        Up2Parser::new(Broken),
+        Err(Up2Error::Read("framing error"))
+    ));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn io_reader_retries_interrupted_reads() {
+    use sma_update_parser::modules::read::IoReader;
+    use std::io::{Cursor, ErrorKind, Read};
+
+    struct Interrupting {
+        inner: Cursor<Vec<u8>>,
+        interrupt: bool,
+    }
+
+    impl Read for Interrupting {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(ErrorKind::Interrupted.into());
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    let file = sample();
+    let reader = IoReader::new(Interrupting {
+        inner: Cursor::new(file.to_bytes()),
+        interrupt: false,
+    });
+    let parsed = Up2File::parse(reader).unwrap();
+    assert_eq!(parsed, Up2File::from_bytes(&file.to_bytes()).unwrap());
+}
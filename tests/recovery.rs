@@ -0,0 +1,139 @@
+use std::ops::Range;
+
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::error::Up2Error;
+use sma_update_parser::modules::parse::Up2Parser;
+use sma_update_parser::modules::slice::Up2Slice;
+use sma_update_parser::modules::types::*;
+
+const RECOVER: ParseOptions = ParseOptions {
+    checksum_mode: ChecksumMode::Strict,
+    max_module_size: 64 * 1024 * 1024,
+    max_file_size: 1024 * 1024 * 1024,
+    recover: true,
+};
+
+fn sample() -> Up2File {
+    let mut data = vec![0x55; 64];
+    // Looks like the header of an empty LevelStart module with a matching checksum
+    data[16..32].copy_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0, 0x7d, 0, 0, 0, 0, 0, 0, 0]);
+    let firmware = FirmwareModule {
+        data_header: SmaDataHeader::default(),
+        delay: 100,
+        data,
+    };
+    Up2Builder::new()
+        .level_start(1)
+        .text("Sunny Boy")
+        .firmware(firmware)
+        .level_end(1)
+        .build()
+}
+
+/// The results of a parser and the ranges it skipped
+type Parsed = (Vec<Result<Module, Up2Error>>, Vec<Range<usize>>);
+
+/// Parses with both parsers in recovery mode
+fn parse_both(bytes: &[u8]) -> [Parsed; 2] {
+    let mut parser = Up2Parser::new(bytes).unwrap().with_config(RECOVER.into());
+    let streamed: Vec<_> = parser.by_ref().collect();
+    let mut slice = Up2Slice::new(bytes).unwrap().with_config(RECOVER.into());
+    let sliced: Vec<_> = slice
+        .by_ref()
+        .map(|m| m.map(ModuleRef::into_owned))
+        .collect();
+    [
+        (streamed, parser.skipped().to_vec()),
+        (sliced, slice.skipped().to_vec()),
+    ]
+}
+
+#[test]
+fn corrupted_module_is_skipped() {
+    let file = sample();
+    let text = file.modules[1].span;
+    let firmware = &file.modules[2];
+    let mut bytes = file.to_bytes();
+    bytes[text.body_offset + 2] ^= 0xff;
+
+    for (results, skipped) in parse_both(&bytes) {
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap(), &file.modules[0]);
+        assert!(matches!(
+            results[1],
+            Err(Up2Error::ChecksumMismatch {
+                module_index: 1,
+                ..
+            })
+        ));
+        let module = results[2].as_ref().unwrap();
+        assert_eq!(module.content, firmware.content);
+        assert_eq!(
+            module.warnings,
+            vec![ModuleWarning::SkippedBytes {
+                start: text.header_offset,
+                end: firmware.span.header_offset,
+            }]
+        );
+        assert_eq!(results[3].as_ref().unwrap(), &file.modules[3]);
+        assert_eq!(
+            skipped,
+            vec![text.header_offset..firmware.span.header_offset]
+        );
+    }
+}
+
+#[test]
+fn header_lookalikes_in_corrupted_data_are_passed_over() {
+    let file = sample();
+    let firmware = file.modules[2].span;
+    let level_end = &file.modules[3];
+    let mut bytes = file.to_bytes();
+    // Corrupt the firmware data in front of the fake LevelStart header
+    bytes[firmware.body_offset + 40] ^= 0xff;
+
+    for (results, skipped) in parse_both(&bytes) {
+        assert_eq!(results.len(), 4);
+        assert!(matches!(
+            results[2],
+            Err(Up2Error::ChecksumMismatch {
+                module_index: 2,
+                ..
+            })
+        ));
+        let module = results[3].as_ref().unwrap();
+        assert_eq!(module.content, level_end.content);
+        assert_eq!(
+            module.warnings,
+            vec![ModuleWarning::SkippedBytes {
+                start: firmware.header_offset,
+                end: level_end.span.header_offset,
+            }]
+        );
+        assert_eq!(
+            skipped,
+            vec![firmware.header_offset..level_end.span.header_offset]
+        );
+    }
+}
+
+#[test]
+fn recovery_ends_when_no_module_follows() {
+    let file = sample();
+    let level_end = file.modules[3].span;
+    let mut bytes = file.to_bytes();
+    // A length that runs past the end of the file
+    bytes[level_end.header_offset + 12] = 0xff;
+
+    for (results, skipped) in parse_both(&bytes) {
+        assert_eq!(results.len(), 4);
+        assert!(matches!(
+            results[3],
+            Err(Up2Error::TruncatedBody {
+                module_index: 3,
+                ..
+            })
+        ));
+        assert_eq!(skipped, vec![level_end.header_offset..bytes.len()]);
+    }
+}
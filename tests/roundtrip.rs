@@ -0,0 +1,163 @@
+use sma_update_parser::modules::config::ParserConfig;
+use sma_update_parser::modules::login::UserGroup;
+use sma_update_parser::modules::slice::Up2Slice;
+use sma_update_parser::modules::span::ModuleSpan;
+use sma_update_parser::modules::types::*;
+
+fn module(module_type: ModuleType, content: ModuleContent) -> Module {
+    Module {
+        header: ModuleHeader {
+            adler: 0,
+            module_type: module_type.into(),
+            susyid: 0x7d,
+            len: 0,
+        },
+        content,
+        span: ModuleSpan::default(),
+        warnings: vec![],
+    }
+}
+
+fn sample() -> Up2File {
+    Up2File {
+        header: Up2Header {
+            header_id: 0x3a414d53,
+            major_version: 1,
+            minor_version: 2,
+            build_number: 3,
+            rev: 4,
+        },
+        modules: vec![
+            module(
+                ModuleType::LevelStartMt,
+                ModuleContent::LevelStart(LevelStartModule { label: 1 }),
+            ),
+            module(
+                ModuleType::FirmwareverMt,
+                ModuleContent::Firmwarever(FirmwareverModule {
+                    major_version: 3,
+                    minor_version: 10,
+                    build_number: 5,
+                    rev: b'R',
+                }),
+            ),
+            module(
+                ModuleType::TextMt,
+                ModuleContent::Text(TextModule {
+                    data: "Sunny Boy".to_string(),
+                }),
+            ),
+            module(
+                ModuleType::LoginMt,
+                ModuleContent::Login(LoginModule {
+                    data_header: SmaDataHeader {
+                        ctrl: 0xa0,
+                        dst_susy: 0xffff,
+                        dst_ser: 0xffffffff,
+                        dst_dev: 0,
+                        dst_fkt: 0,
+                        src_susy: 0x78,
+                        src_ser: 1234,
+                        src_dev: 0,
+                        src_fkt: 0,
+                        cmd: 0x0c,
+                        pcnt: 0x80,
+                        obj_num: 0xfffd,
+                        dat_len: 32,
+                        p0: 7,
+                    },
+                    p1: 900,
+                    p2: 0,
+                    p3: 0,
+                    password: LoginModule::encode_password(UserGroup::User, "0000"),
+                    mode: 7,
+                }),
+            ),
+            module(
+                ModuleType::FirmwareMt,
+                ModuleContent::Firmware(FirmwareModule {
+                    data_header: SmaDataHeader {
+                        ctrl: 0xa0,
+                        dst_susy: 0x7d,
+                        dst_ser: 0xffffffff,
+                        dst_dev: 0,
+                        dst_fkt: 0,
+                        src_susy: 0x78,
+                        src_ser: 1234,
+                        src_dev: 0,
+                        src_fkt: 0,
+                        cmd: 0x0e,
+                        pcnt: 0,
+                        obj_num: 0,
+                        dat_len: 8,
+                        p0: 0,
+                    },
+                    delay: 100,
+                    data: vec![1, 2, 3, 4, 5, 6, 7, 8],
+                }),
+            ),
+            module(
+                ModuleType::UpFmt10Mt,
+                ModuleContent::UpFmt10(UpFmt10Module {
+                    data: vec![0xde, 0xad, 0xbe, 0xef],
+                }),
+            ),
+            module(
+                ModuleType::Unknown(0x4242),
+                ModuleContent::Unknown(UnknownModule {
+                    module_type: 0x4242,
+                    data: vec![9; 5],
+                }),
+            ),
+            module(
+                ModuleType::LevelEndMt,
+                ModuleContent::LevelEnd(LevelEndModule { label: 1 }),
+            ),
+        ],
+    }
+}
+
+#[test]
+fn parse_then_write_gives_identical_bytes() {
+    let bytes = sample().to_bytes();
+    let parsed = Up2File::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed.to_bytes(), bytes);
+
+    let streamed = Up2File::parse(&bytes[..]).unwrap();
+    assert_eq!(streamed, parsed);
+}
+
+#[test]
+fn wrong_checksums_are_kept_until_the_module_changes() {
+    let mut bytes = sample().to_bytes();
+    let text = Up2File::from_bytes(&bytes).unwrap().modules[2].span;
+    // A checksum that does not match the text module
+    bytes[text.header_offset] ^= 0xff;
+
+    let slice = Up2Slice::new(&bytes)
+        .unwrap()
+        .with_config(ParserConfig::new().with_checksum_mode(ChecksumMode::Lenient));
+    let parsed = Up2File {
+        header: slice.header,
+        modules: slice.map(|m| m.unwrap().into_owned()).collect(),
+    };
+    assert!(!parsed.modules[2].warnings.is_empty());
+    assert_eq!(parsed.to_bytes(), bytes);
+
+    let mut changed = parsed.clone();
+    changed.modules[2].content = ModuleContent::Text(TextModule {
+        data: "Sunny Tripower".to_string(),
+    });
+    let reparsed = Up2File::from_bytes(&changed.to_bytes()).unwrap();
+    assert_eq!(reparsed.modules[2].content, changed.modules[2].content);
+}
+
+#[test]
+fn written_modules_parse_to_the_same_content() {
+    let file = sample();
+    let parsed = Up2File::from_bytes(&file.to_bytes()).unwrap();
+    assert_eq!(parsed.header, file.header);
+    let contents: Vec<_> = parsed.modules.into_iter().map(|m| m.content).collect();
+    let expected: Vec<_> = file.modules.into_iter().map(|m| m.content).collect();
+    assert_eq!(contents, expected);
+}
@@ -0,0 +1,51 @@
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::parse::Up2Parser;
+use sma_update_parser::modules::slice::Up2Slice;
+use sma_update_parser::modules::types::*;
+
+#[test]
+fn slice_modules_borrow_from_the_input() {
+    let firmware = FirmwareModule {
+        data_header: SmaDataHeader::default(),
+        delay: 100,
+        data: vec![0x55; 64],
+    };
+    let bytes = Up2Builder::new()
+        .text("Sunny Boy")
+        .firmware(firmware)
+        .up_fmt10(vec![1, 2, 3])
+        .unknown(0x4242, vec![9; 5])
+        .build()
+        .to_bytes();
+    let input = bytes.as_ptr_range();
+    let borrowed = |data: &[u8]| {
+        let range = data.as_ptr_range();
+        input.start <= range.start && range.end <= input.end
+    };
+
+    let modules: Vec<ModuleRef> = Up2Slice::new(&bytes)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(modules.len(), 4);
+    for module in &modules {
+        let data = match &module.content {
+            ModuleContentRef::Text(m) => m.data.as_bytes(),
+            ModuleContentRef::Firmware(m) => m.data,
+            ModuleContentRef::UpFmt10(m) => m.data,
+            ModuleContentRef::Unknown(m) => m.data,
+            content => panic!("Unexpected module {:?}", content),
+        };
+        assert!(borrowed(data));
+        // The variable length data is at the end of the body
+        let body_end = module.span.body_offset + module.span.len;
+        assert_eq!(data, &bytes[body_end - data.len()..body_end]);
+    }
+
+    let owned: Vec<Module> = modules.into_iter().map(ModuleRef::into_owned).collect();
+    let streamed: Vec<Module> = Up2Parser::new(&bytes[..])
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(owned, streamed);
+}
@@ -0,0 +1,65 @@
+use sma_update_parser::modules::smadata::{Command, Ctrl, PacketCounter};
+use sma_update_parser::modules::types::SmaDataHeader;
+
+#[test]
+fn ctrl_shows_known_and_unknown_flags() {
+    assert_eq!(Ctrl(0x000e).to_string(), "14 words, flags 0x00");
+    assert_eq!(Ctrl(0x800e).to_string(), "14 words, flags 0x80 (group)");
+    assert_eq!(
+        Ctrl(0xc00e).to_string(),
+        "14 words, flags 0xc0 (group, response)"
+    );
+    assert_eq!(Ctrl(0x0a0e).to_string(), "14 words, flags 0x0a (0x0a)");
+    assert_eq!(
+        Ctrl(0xc10e).to_string(),
+        "14 words, flags 0xc1 (group, response, 0x01)"
+    );
+    let ctrl = Ctrl(0x400e);
+    assert_eq!(ctrl.len_words(), 14);
+    assert!(ctrl.is_response() && !ctrl.is_group());
+}
+
+#[test]
+fn commands_show_their_name_and_code() {
+    assert_eq!(Command::from_code(12), Command::SetData);
+    assert_eq!(Command::SetData.to_string(), "CMD_SET_DATA (0x0c)");
+    assert_eq!(Command::from_code(0x99), Command::Other(0x99));
+    assert_eq!(Command::Other(0x99).to_string(), "0x99");
+    assert_eq!(Command::Other(0x99).name(), None);
+    for code in 0..=u8::MAX {
+        assert_eq!(Command::from_code(code).code(), code);
+    }
+}
+
+#[test]
+fn packet_counter_shows_the_first_fragment() {
+    assert_eq!(PacketCounter(0x05).to_string(), "5");
+    assert_eq!(PacketCounter(0x85).to_string(), "5, first fragment");
+    assert_eq!(PacketCounter(0x85).count(), 5);
+}
+
+#[test]
+fn header_is_shown_with_typed_fields() {
+    let header = SmaDataHeader {
+        ctrl: 0xa00e,
+        dst_susy: 0xffff,
+        dst_ser: 0xffffffff,
+        dst_dev: 0,
+        dst_fkt: 0,
+        src_susy: 0x78,
+        src_ser: 1234,
+        src_dev: 1,
+        src_fkt: 2,
+        cmd: 0x0c,
+        pcnt: 0x80,
+        obj_num: 0xfffd,
+        dat_len: 32,
+        p0: 7,
+    };
+    assert_eq!(
+        header.to_string(),
+        "ctrl: 14 words, flags 0xa0 (group, 0x20), cmd: CMD_SET_DATA (0x0c), pcnt: 0, first fragment, \
+         dst: all devices dev 0x00 fkt 0x00, src: SUSy-ID 120 SN 1234 dev 0x01 fkt 0x02, \
+         obj_num: 0xfffd, dat_len: 32, p0: 0x00000007"
+    );
+}
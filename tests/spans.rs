@@ -0,0 +1,82 @@
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::span::{FieldSpan, FileFieldSpan, ModuleSpan};
+use sma_update_parser::modules::types::*;
+
+#[test]
+fn spans_point_at_the_module_bytes() {
+    let firmware = FirmwareModule {
+        data_header: SmaDataHeader::default(),
+        delay: 0x01020304,
+        data: vec![0xaa; 20],
+    };
+    let built = Up2Builder::new()
+        .level_start(7)
+        .text("Sunny Boy")
+        .firmware(firmware)
+        .build();
+    let bytes = built.to_bytes();
+    let file = Up2File::from_bytes(&bytes).unwrap();
+
+    let spans: Vec<ModuleSpan> = file.modules.iter().map(|m| m.span).collect();
+    assert_eq!(
+        spans,
+        vec![
+            ModuleSpan {
+                header_offset: 8,
+                body_offset: 24,
+                len: 4
+            },
+            ModuleSpan {
+                header_offset: 28,
+                body_offset: 44,
+                len: 9
+            },
+            ModuleSpan {
+                header_offset: 53,
+                body_offset: 69,
+                len: 52
+            },
+        ]
+    );
+    let built_spans: Vec<ModuleSpan> = built.modules.iter().map(|m| m.span).collect();
+    assert_eq!(spans, built_spans);
+
+    let bytes_of = |f: FileFieldSpan| &bytes[f.offset..f.offset + f.len];
+    let level_start = &file.modules[0];
+    // The layout is relative to the body, the spans of a module are absolute
+    assert_eq!(
+        level_start.content.fields(),
+        [FieldSpan {
+            name: "label",
+            offset: 0,
+            len: 4
+        }]
+    );
+    assert_eq!(
+        level_start.field_spans(),
+        vec![FileFieldSpan {
+            name: "label",
+            offset: 24,
+            len: 4
+        }]
+    );
+    assert_eq!(
+        bytes_of(level_start.field_span("label").unwrap()),
+        [7, 0, 0, 0]
+    );
+
+    let text = &file.modules[1];
+    assert_eq!(bytes_of(text.field_span("data").unwrap()), b"Sunny Boy");
+
+    let firmware = &file.modules[2];
+    assert_eq!(
+        bytes_of(firmware.field_span("delay").unwrap()),
+        0x01020304u32.to_le_bytes()
+    );
+    assert_eq!(
+        bytes_of(firmware.field_span("dat_len").unwrap()),
+        24u16.to_le_bytes()
+    );
+    assert_eq!(bytes_of(firmware.field_span("data").unwrap()), [0xaa; 20]);
+    assert_eq!(firmware.field_span("password"), None);
+}
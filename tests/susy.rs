@@ -0,0 +1,67 @@
+use sma_update_parser::modules::susy::{self, SusyCatalog, SusyEntry, SusyFileError};
+
+#[test]
+fn builtin_table_has_no_broadcast_entry() {
+    assert_eq!(
+        susy::describe(0x015d).map(|e| e.to_string()).as_deref(),
+        Some("SMA Energy Meter 2.0")
+    );
+    assert_eq!(susy::describe(0xffff), None);
+}
+
+#[test]
+fn catalog_file_entries_are_loaded() {
+    let mut catalog = SusyCatalog::new();
+    catalog
+        .load_str(
+            "# id, family, model\n\
+             \n\
+             0x1A2b, Sunny Boy, Sunny Boy 3.0\n\
+             \x20 4242 ,Sunny Tripower,  Sunny Tripower X, 25 kW\n\
+             0x015d, Energy Meter, Energy Meter (renamed)\n",
+        )
+        .unwrap();
+
+    let entry = catalog.describe(0x1a2b).unwrap();
+    assert_eq!(entry.family, "Sunny Boy");
+    assert_eq!(entry.model, "Sunny Boy 3.0");
+    // The model is the rest of the line, including further commas
+    assert_eq!(
+        catalog.describe(4242).map(SusyEntry::to_string).as_deref(),
+        Some("Sunny Tripower X, 25 kW")
+    );
+    // File entries take precedence over built-in ones
+    assert_eq!(
+        catalog.describe(0x015d).unwrap().model,
+        "Energy Meter (renamed)"
+    );
+    assert_eq!(
+        catalog.describe(0x0174).unwrap().model,
+        "Sunny Home Manager 2.0"
+    );
+}
+
+#[test]
+fn invalid_catalog_lines_are_reported() {
+    let mut catalog = SusyCatalog::new();
+    let error = catalog
+        .load_str("# comment\n0x10, Sunny Boy, Sunny Boy 3.0\n0xzz, Sunny Boy, Sunny Boy 4.0\n")
+        .unwrap_err();
+    assert_eq!(
+        error,
+        SusyFileError {
+            line: 3,
+            reason: "invalid id"
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "Invalid SUSy-ID entry in line 3: invalid id"
+    );
+    // Nothing is added from a file with errors
+    assert_eq!(catalog.describe(0x10), None);
+
+    let error = catalog.load_str("0x10, Sunny Boy\n").unwrap_err();
+    assert_eq!(error.line, 1);
+    assert_eq!(error.reason, "expected id, family and model");
+}
@@ -0,0 +1,44 @@
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::smadata::Target;
+use sma_update_parser::modules::types::*;
+
+fn logout(dst_susy: u16, dst_ser: u32) -> LogoutModule {
+    LogoutModule {
+        data_header: SmaDataHeader {
+            dst_susy,
+            dst_ser,
+            ..Default::default()
+        },
+    }
+}
+
+#[test]
+fn targets_distinguish_broadcast_susy_and_serial() {
+    let file = Up2Builder::new()
+        .logout(logout(0xffff, 0xffffffff))
+        .logout(logout(0x7d, 0xffffffff))
+        .logout(logout(0x7d, 2130012345))
+        .logout(logout(0xffff, 2130012345))
+        .logout(logout(0x7d, 0xffffffff))
+        .build();
+    let targets = file.targets();
+    assert_eq!(
+        targets,
+        [
+            Target::Broadcast,
+            Target::AnyOf { susy: 0x7d },
+            Target::Device {
+                susy: Some(0x7d),
+                serial: 2130012345
+            },
+            Target::Device {
+                susy: None,
+                serial: 2130012345
+            },
+        ]
+    );
+    assert_eq!(targets[2].to_string(), "SUSy-ID 125 SN 2130012345");
+    assert!(targets[1].matches(0x7d, 1));
+    assert!(!targets[2].matches(0x7e, 2130012345));
+    assert!(targets[3].matches(0x7e, 2130012345));
+}
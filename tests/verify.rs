@@ -0,0 +1,191 @@
+use sma_update_parser::modules::builder::Up2Builder;
+use sma_update_parser::modules::digest::{md4, Crc16};
+use sma_update_parser::modules::types::*;
+
+fn hex(digest: [u8; 16]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn digests_match_reference_values() {
+    assert_eq!(hex(md4(b"")), "31d6cfe0d16ae931b73c59d7e0c089c0");
+    assert_eq!(hex(md4(b"abc")), "a448017aaf21d8525fc10ae87aa6729d");
+    assert_eq!(
+        hex(md4(
+            b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+        )),
+        "e33b4ddc9c38f2199c3e7b164fcc0536"
+    );
+    assert_eq!(Crc16::CCITT_FALSE.checksum(b"123456789"), 0x29b1);
+}
+
+/// Firmware data with the blocks `numbers`, every byte of block n is n + 1
+fn firmware(numbers: std::ops::Range<u32>, target: (u16, u32)) -> FirmwareModule {
+    let mut data = vec![];
+    for number in numbers {
+        data.extend_from_slice(&number.to_le_bytes());
+        data.extend_from_slice(&[number as u8 + 1; 128]);
+    }
+    FirmwareModule {
+        data_header: SmaDataHeader {
+            dst_susy: target.0,
+            dst_ser: target.1,
+            ..Default::default()
+        },
+        delay: 0,
+        data,
+    }
+}
+
+/// A FwChk module for `blk_first..=blk_last` with the digests of `covered`
+fn fw_chk(blk_first: u32, blk_last: u32, covered: &[u8], target: (u16, u32)) -> FwChkModule {
+    FwChkModule {
+        data_header: SmaDataHeader {
+            dst_susy: target.0,
+            dst_ser: target.1,
+            ..Default::default()
+        },
+        blk_first,
+        blk_last,
+        cond_cnt: 0,
+        crc: Crc16::CCITT_FALSE.checksum(covered),
+        adler32: adler32::RollingAdler32::from_buffer(covered).hash(),
+        md4: md4(covered),
+    }
+}
+
+const DEVICE: (u16, u32) = (0xffff, 0xffffffff);
+
+#[test]
+fn fw_chk_is_verified_against_the_firmware_blocks() {
+    // Blocks 0 to 2, only 1 and 2 are covered by the check
+    let covered = [[2u8; 128], [3u8; 128]].concat();
+    let fw_chk = fw_chk(1, 2, &covered, DEVICE);
+    let mut broken = fw_chk.clone();
+    broken.crc ^= 1;
+    let file = Up2Builder::new()
+        .firmware(firmware(0..3, DEVICE))
+        .fw_chk(fw_chk)
+        .fw_chk(broken)
+        .build();
+
+    // The CRC is not checked without a CRC-16 variant
+    let reports = file.verify_fw_chks();
+    assert_eq!(reports.len(), 2);
+    assert!(reports.iter().all(|r| r.crc.is_none() && r.passed()));
+
+    let reports = file.verify_fw_chks_with(&Crc16::CCITT_FALSE);
+    assert!(reports[0].passed(), "{:?}", reports[0]);
+    assert_eq!(reports[0].blocks_found, 2);
+    // The second check covers the same firmware module
+    assert_eq!(reports[1].blocks_found, 2);
+    assert!(!reports[1].passed());
+    assert!(!reports[1].crc.unwrap().passed());
+    assert!(reports[1].adler32.passed() && reports[1].md4.passed());
+
+    let identified = file.identify_crc16();
+    assert!(identified[0]
+        .1
+        .iter()
+        .any(|m| m.variant == Crc16::CCITT_FALSE && !m.byte_swapped));
+}
+
+#[test]
+fn fw_chk_covers_blocks_from_all_preceding_firmware_modules() {
+    let all: Vec<u8> = (1u8..=4).flat_map(|b| [b; 128]).collect();
+    let file = Up2Builder::new()
+        .firmware(firmware(0..2, DEVICE))
+        .fw_chk(fw_chk(0, 1, &all[..256], DEVICE))
+        .firmware(firmware(2..4, DEVICE))
+        .fw_chk(fw_chk(0, 3, &all, DEVICE))
+        .build();
+
+    let reports = file.verify_fw_chks();
+    assert_eq!(reports.len(), 2);
+    assert!(reports.iter().all(|r| r.passed()), "{:?}", reports);
+    assert_eq!(reports[1].blocks_found, 4);
+}
+
+#[test]
+fn fw_chk_is_scoped_by_level_and_target() {
+    let other = (0x010e, 1234);
+    let covered = [[1u8; 128], [2u8; 128]].concat();
+    let file = Up2Builder::new()
+        .level_start(1)
+        .firmware(firmware(0..2, DEVICE))
+        .level_end(1)
+        .level_start(2)
+        .firmware(firmware(0..2, other))
+        .firmware(firmware(0..2, DEVICE))
+        .fw_chk(fw_chk(0, 1, &covered, DEVICE))
+        .level_end(2)
+        .build();
+
+    let reports = file.verify_fw_chks();
+    assert_eq!(reports.len(), 1);
+    // Neither the firmware in level 1 nor the one for the other target is counted twice
+    assert_eq!(reports[0].blocks_found, 2);
+    assert!(reports[0].passed(), "{:?}", reports[0]);
+}
+
+#[test]
+fn repeated_blocks_do_not_hide_missing_ones() {
+    // Block 1 is missing and block 0 is sent twice
+    let covered = [[1u8; 128], [1u8; 128]].concat();
+    let file = Up2Builder::new()
+        .firmware(firmware(0..1, DEVICE))
+        .firmware(firmware(0..1, DEVICE))
+        .fw_chk(fw_chk(0, 1, &covered, DEVICE))
+        .build();
+
+    let reports = file.verify_fw_chks();
+    assert_eq!(reports[0].blocks_found, 1);
+    assert_eq!(reports[0].blocks_expected(), 2);
+    // The digests match the data, only the missing block fails the check
+    assert!(reports[0].adler32.passed() && reports[0].md4.passed());
+    assert!(!reports[0].passed());
+}
+
+#[test]
+fn block_range_is_counted_without_overflow() {
+    let file = Up2Builder::new()
+        .fw_chk(fw_chk(0, u32::MAX, &[], DEVICE))
+        .build();
+    let reports = file.verify_fw_chks();
+    assert_eq!(reports[0].blocks_expected(), 1 << 32);
+    assert!(!reports[0].passed());
+}
+
+#[test]
+fn crc16_catalogue_matches_check_values() {
+    let expected = [
+        ("CRC-16/ARC", 0xbb3d),
+        ("CRC-16/CCITT-FALSE", 0x29b1),
+        ("CRC-16/XMODEM", 0x31c3),
+        ("CRC-16/KERMIT", 0x2189),
+        ("CRC-16/X-25", 0x906e),
+        ("CRC-16/MODBUS", 0x4b37),
+        ("CRC-16/AUG-CCITT", 0xe5cc),
+        ("CRC-16/BUYPASS", 0xfee8),
+        ("CRC-16/CDMA2000", 0x4c06),
+        ("CRC-16/DDS-110", 0x9ecf),
+        ("CRC-16/DECT-X", 0x007f),
+        ("CRC-16/DNP", 0xea82),
+        ("CRC-16/EN-13757", 0xc2b7),
+        ("CRC-16/GENIBUS", 0xd64e),
+        ("CRC-16/MAXIM", 0x44c2),
+        ("CRC-16/MCRF4XX", 0x6f91),
+        ("CRC-16/RIELLO", 0x63d0),
+        ("CRC-16/T10-DIF", 0xd0db),
+        ("CRC-16/TELEDISK", 0x0fb3),
+        ("CRC-16/TMS37157", 0x26b1),
+        ("CRC-16/USB", 0xb4c8),
+        ("CRC-16/A", 0xbf05),
+    ];
+    assert_eq!(Crc16::CATALOGUE.len(), expected.len());
+    for (crc, (name, check)) in Crc16::CATALOGUE.iter().zip(expected) {
+        assert_eq!(crc.name, name);
+        assert_eq!(crc.checksum(b"123456789"), check, "{}", name);
+    }
+    assert_eq!(Crc16::by_name("x-25"), Some(Crc16::X_25));
+}